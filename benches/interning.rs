@@ -0,0 +1,88 @@
+use criterion::{black_box, criterion_group, Criterion, Throughput};
+use desolation::lex::Lexer;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts every allocation so the benchmark can report how many the lexer makes.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// A program with `functions` functions, each using a handful of locals and
+/// calling the previous function, so that most identifiers repeat.
+fn generate(functions: usize) -> String {
+    let mut source = String::new();
+    for i in 0..functions {
+        source.push_str(&format!(
+            "fun f{i}(alpha, beta) {{\n    var gamma\n    var delta\n    gamma : .alpha * .beta + f{prev}(.alpha, .beta)\n    delta : .gamma - .alpha\n    loop {{\n        until .delta <= 0\n        delta : .delta - .beta\n    }}\n    return .gamma + .delta\n}}\n\n",
+            i = i,
+            prev = i.saturating_sub(1)
+        ));
+    }
+    source
+}
+
+fn count_allocations(source: &str) -> (usize, usize, usize) {
+    let (allocations, bytes) = (
+        ALLOCATIONS.load(Ordering::Relaxed),
+        ALLOCATED_BYTES.load(Ordering::Relaxed),
+    );
+    let tokens = Lexer::new().lex_str(source).unwrap().tokens.len();
+    (
+        tokens,
+        ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes,
+    )
+}
+
+// Identifiers are interned, so once every name has been seen, lexing a
+// program again should only allocate for the token vector itself.
+fn report_allocations(source: &str) {
+    let (tokens, cold, cold_bytes) = count_allocations(source);
+    let (_, warm, warm_bytes) = count_allocations(source);
+    println!(
+        "lexed {} bytes into {} tokens: {} allocations ({} bytes) with a cold interner, {} allocations ({} bytes) with a warm one ({:.4} per token)",
+        source.len(),
+        tokens,
+        cold,
+        cold_bytes,
+        warm,
+        warm_bytes,
+        warm as f64 / tokens as f64
+    );
+}
+
+fn lex_identifiers(c: &mut Criterion) {
+    let source = generate(20_000);
+    report_allocations(&source);
+    let mut group = c.benchmark_group("lex_identifiers");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(source.len() as u64));
+    group.bench_function("generated", |b| {
+        b.iter(|| Lexer::new().lex_str(black_box(&source)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, lex_identifiers);
+
+fn main() {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
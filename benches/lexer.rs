@@ -0,0 +1,37 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use desolation::lex::Lexer;
+
+const SQ: &str = include_str!("../examples/sq.t");
+
+/// Repeats `examples/sq.t` (plus a line with multi-byte characters) until the
+/// source is at least `size` bytes long.
+fn generate(size: usize) -> String {
+    let mut source = String::with_capacity(size + SQ.len());
+    let mut i = 0;
+    while source.len() < size {
+        source.push_str(&SQ.replace("sq", &format!("sq{}", i)));
+        source.push_str("\nvar ünïcødé\nsprint(\"héllo wörld ✓\")\n");
+        i += 1;
+    }
+    source
+}
+
+// Throughput (bytes/s) should stay flat as the input grows; a quadratic
+// lexer shows up as throughput halving every time the size doubles.
+fn lex_scaling(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lex_scaling");
+    group.sample_size(10);
+    for mb in [1, 2, 4, 8] {
+        let source = generate(mb * 1024 * 1024);
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{}MiB", mb)),
+            &source,
+            |b, s| b.iter(|| Lexer::new().lex(black_box(s.clone())).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lex_scaling);
+criterion_main!(benches);
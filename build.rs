@@ -0,0 +1,145 @@
+//! Generates the LALR(1) tables for `parser::Parser::parse_lr` from the
+//! grammar in `src/parser/grammar.rs` and the operator table in
+//! `src/parser/precedence.rs`, failing the build on any conflict.
+
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+#[allow(dead_code)]
+#[path = "src/parser/lalr.rs"]
+mod lalr;
+
+#[allow(dead_code)]
+#[path = "src/parser/grammar.rs"]
+mod grammar;
+
+#[allow(dead_code)]
+#[path = "src/parser/precedence.rs"]
+mod precedence;
+
+// The library logs through `log`, which the build script doesn't depend on.
+macro_rules! trace {
+    ($($arg:tt)*) => {};
+}
+
+#[allow(dead_code)]
+#[path = "src/lex/types.rs"]
+mod types;
+
+// So that `crate::lex::types` names the same module here as in the library.
+mod lex {
+    pub(crate) use super::types;
+}
+
+use grammar::Terminal;
+use precedence::{Fixity, OPERATORS};
+
+// The binary operators, which the table has the same states for.
+fn binary_operators() -> Vec<Terminal> {
+    OPERATORS
+        .iter()
+        .filter(|op| matches!(op.fixity, Fixity::Infix(_)))
+        .map(|op| Terminal::syntax(op.token))
+        .collect()
+}
+
+// The tokens an operand can start with.
+fn expression_starts() -> Vec<Terminal> {
+    let prefixes = OPERATORS
+        .iter()
+        .filter(|op| op.fixity == Fixity::Prefix)
+        .map(|op| Terminal::syntax(op.token));
+    [
+        Terminal::Identifier,
+        Terminal::Integer,
+        Terminal::String,
+        Terminal::Character,
+        Terminal::LParen,
+    ]
+    .into_iter()
+    .chain(prefixes)
+    .collect()
+}
+
+// What a state expects, for syntax errors: "an expression", "':' or newline", ...
+fn describe_expected(valid: &[Terminal]) -> String {
+    let mut rest = valid.to_vec();
+    let mut parts = Vec::new();
+    let starts = expression_starts();
+    if starts.iter().all(|t| valid.contains(t)) {
+        rest.retain(|t| !starts.contains(t));
+        parts.push("an expression");
+    }
+    let operators = binary_operators();
+    if operators.iter().all(|t| valid.contains(t)) {
+        rest.retain(|t| !operators.contains(t));
+        parts.push("an operator");
+    }
+    parts.extend(rest.iter().map(|t| t.name()));
+    match parts.split_last() {
+        Some((last, [])) => last.to_string(),
+        Some((last, init)) => format!("{} or {}", init.join(", "), last),
+        None => "nothing".to_string(),
+    }
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/parser/lalr.rs");
+    println!("cargo:rerun-if-changed=src/parser/grammar.rs");
+    println!("cargo:rerun-if-changed=src/parser/precedence.rs");
+    println!("cargo:rerun-if-changed=src/lex/types.rs");
+
+    let table = lalr::Table::build(&grammar::grammar());
+    if !table.conflicts.is_empty() {
+        let mut report = String::new();
+        for conflict in &table.conflicts {
+            writeln!(report, "{}", conflict).unwrap();
+        }
+        panic!("the grammar is not LALR(1):\n\n{}", report);
+    }
+
+    let states = table.actions.len();
+    let mut out = String::new();
+    writeln!(out, "pub(super) const STATES: usize = {};", states).unwrap();
+    writeln!(
+        out,
+        "pub(super) static ACTIONS: [[Option<ParserActions>; {}]; STATES] = [",
+        Terminal::ALL.len()
+    )
+    .unwrap();
+    for row in &table.actions {
+        writeln!(out, "    {:?},", row).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+    writeln!(
+        out,
+        "pub(super) static GOTOS: [[Option<u16>; {}]; STATES] = [",
+        grammar::NonTerminal::ALL.len()
+    )
+    .unwrap();
+    for row in &table.gotos {
+        let row = row
+            .iter()
+            .map(|s| s.map(|s| u16::try_from(s).unwrap()))
+            .collect::<Vec<_>>();
+        writeln!(out, "    {:?},", row).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+    writeln!(out, "pub(super) static EXPECTED: [&str; STATES] = [").unwrap();
+    for row in &table.actions {
+        let valid = Terminal::ALL
+            .iter()
+            .zip(row)
+            .filter(|(_, action)| action.is_some())
+            .map(|(t, _)| *t)
+            .collect::<Vec<_>>();
+        writeln!(out, "    {:?},", describe_expected(&valid)).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("lalr_table.rs");
+    fs::write(path, out).unwrap();
+}
@@ -0,0 +1,20 @@
+# Sums the numbers from 1 to n.
+var total   # running sum
+
+
+fun sum(n) {
+	var i
+	i : 1
+	total : 0
+	loop {
+		until .i > .n   # inclusive
+		total : .total + .i
+		i : .i + 1
+	}
+	return .total
+}
+
+fun init() {
+    iprint(sum(10))  
+    nl()
+}
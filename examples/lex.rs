@@ -1,14 +1,11 @@
 use desolation::lex::Lexer;
 use log::{error, info};
-use std::fs;
 
 fn main() {
     sensible_env_logger::init!();
     let mut lexer = Lexer::new();
 
-    let source = fs::read_to_string("examples/sq.t").unwrap();
-    info!("{:#?}", source);
-    let tokens = lexer.lex(source);
+    let tokens = lexer.lex_file("examples/sq.t");
     match tokens {
         Ok(tokens) => {
             for token in &tokens.tokens {
@@ -0,0 +1,43 @@
+#![no_main]
+
+use desolation::lex::{Lexer, LexerConfig, TextEdit, TokenKind};
+use libfuzzer_sys::fuzz_target;
+
+// Lexes arbitrary bytes in every mode. Any panic, or a run that doesn't end,
+// is a bug. Inputs that are valid UTF-8 are also checked for a lossless round
+// trip, lexed again repeated into a long input, and relexed after deleting
+// their first half.
+fuzz_target!(|data: &[u8]| {
+    let _ = Lexer::new().lex_reader(data);
+    let Ok(source) = std::str::from_utf8(data) else {
+        return;
+    };
+    let (stream, _, _) = Lexer::new().lossless(true).lex_recovering(source);
+    assert_eq!(stream.tokens.last().map(|t| t.kind()), Some(TokenKind::Eof));
+    assert_eq!(stream.to_source(), source);
+    let _ = Lexer::with_config(LexerConfig::extended()).lex_recovering(source);
+
+    // libFuzzer keeps inputs short, but some bugs only show on long runs of
+    // the same thing, like thousands of comments in a row.
+    if !source.is_empty() && source.len() <= 64 {
+        let long = source.repeat(64 * 1024 / source.len());
+        let (stream, _, _) = Lexer::new().lex_recovering(&long);
+        assert_eq!(stream.tokens.last().map(|t| t.kind()), Some(TokenKind::Eof));
+        let insignificant = LexerConfig {
+            significant_newlines: false,
+            ..LexerConfig::extended()
+        };
+        let _ = Lexer::with_config(insignificant).lex_recovering(&long);
+    }
+
+    let mut mid = source.len() / 2;
+    while !source.is_char_boundary(mid) {
+        mid -= 1;
+    }
+    let edit = TextEdit::delete(0..mid);
+    let relexed = Lexer::new().relex(&stream, edit.clone());
+    let (expected, _, _) = Lexer::new()
+        .lossless(true)
+        .lex_recovering(&edit.apply(source));
+    assert_eq!(relexed.tokens, expected.tokens);
+});
@@ -1,29 +1,184 @@
 mod node;
 
-pub enum Program {
-    VarList(Vec<Var>),
-    FunList(Vec<Fun>),
+use crate::source::Span;
+use serde::{Deserialize, Serialize};
+
+pub use node::{NodeId, NodeIds};
+
+/// A parsed file, its declarations in source order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Program {
+    pub items: Vec<Item>,
+}
+
+impl Program {
+    pub fn vars(&self) -> impl Iterator<Item = &Var> {
+        self.items.iter().filter_map(|item| match item {
+            Item::Var(var) => Some(var),
+            _ => None,
+        })
+    }
+
+    pub fn funs(&self) -> impl Iterator<Item = &Fun> {
+        self.items.iter().filter_map(|item| match item {
+            Item::Fun(fun) => Some(fun),
+            _ => None,
+        })
+    }
+
+    /// The first item declaring `name`, wherever it is in the file: a function
+    /// may use globals and functions declared below it.
+    pub fn item(&self, name: &str) -> Option<&Item> {
+        self.items.iter().find(|item| item.name() == name)
+    }
+}
+
+/// A top-level declaration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Item {
+    /// A global variable.
+    Var(Var),
+    Fun(Fun),
+}
+
+impl Item {
+    pub fn id(&self) -> NodeId {
+        match self {
+            Item::Var(var) => var.id,
+            Item::Fun(fun) => fun.id,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Item::Var(var) => var.span,
+            Item::Fun(fun) => fun.span,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Item::Var(var) => &var.name,
+            Item::Fun(fun) => &fun.name,
+        }
+    }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ID {
+    pub id: NodeId,
+    pub span: Span,
     pub name: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Var {
+    pub id: NodeId,
+    /// From `var` to the end of the value, without the doc comment.
+    pub span: Span,
+    /// The doc comment lines written above the declaration.
+    pub docs: Vec<String>,
     pub name: String,
     pub value: Option<Expr>,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Expr {
+    pub id: NodeId,
+    pub span: Span,
+    pub kind: ExprKind,
+}
+
+impl Expr {
+    pub fn new(id: NodeId, span: Span, kind: ExprKind) -> Self {
+        Expr { id, span, kind }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExprKind {
+    /// May be `i64::MAX + 1`, which is only valid as the operand of a unary minus.
+    Integer(u64),
+    String(String),
+    Character(char),
+    /// A variable's name, which evaluates to its address.
+    Var(String),
+    /// `.x`, the value stored at the address `x` evaluates to.
+    Deref(Box<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Box<Expr>, Vec<Expr>),
+    /// A parenthesized expression, kept so the source can be reproduced.
+    Group(Box<Expr>),
+}
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum UnaryOp {
+    Neg,
+    Not,
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    And,
+    Or,
+    Xor,
+    Eq,
+    Neq,
+    Lt,
+    Leq,
+    Gt,
+    Geq,
+    LShift,
+    RShift,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Fun {
+    pub id: NodeId,
+    /// From `fun` to the closing `}`, without the doc comment.
+    pub span: Span,
+    /// The doc comment lines written above the declaration.
+    pub docs: Vec<String>,
     pub name: String,
     pub params: Vec<ID>,
     pub body: Vec<Stmt>,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Stmt {
+    pub id: NodeId,
+    pub span: Span,
+    pub kind: StmtKind,
+}
+
+impl Stmt {
+    pub fn new(id: NodeId, span: Span, kind: StmtKind) -> Self {
+        Stmt { id, span, kind }
+    }
+}
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StmtKind {
+    Var(Var),
+    /// `target : value` stores `value` at the address `target` evaluates to.
+    Assign {
+        target: Expr,
+        value: Expr,
+    },
+    Expr(Expr),
+    If {
+        cond: Expr,
+        then_body: Vec<Stmt>,
+        else_body: Option<Vec<Stmt>>,
+    },
+    Loop(Vec<Stmt>),
+    /// Leaves the innermost `loop` once `cond` holds.
+    Until(Expr),
+    Return(Option<Expr>),
 }
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+/// Identifies a node of one parsed program. The parsers number nodes in the
+/// order they finish them, so children come before their parents.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct NodeId(pub u32);
+
+/// Hands out `NodeId`s, counting up from zero.
+#[derive(Debug, Default, Clone)]
+pub struct NodeIds {
+    next: u32,
+}
+
+impl NodeIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn next_id(&mut self) -> NodeId {
+        let id = NodeId(self.next);
+        self.next += 1;
+        id
+    }
+}
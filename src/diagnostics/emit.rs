@@ -0,0 +1,345 @@
+use crate::diagnostics::{Diagnostic, Label, Report, Severity, Suggestion};
+use crate::source::{FileId, LineIndex, SourceMap, WideEncoding};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::ops::Range;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Collects the diagnostics of a compilation and serializes them for tools,
+/// either as JSON lines (one object per diagnostic) or as a SARIF 2.1 log for
+/// code scanning services.
+///
+/// Positions are 1-based lines and columns counted in chars, or in UTF-16
+/// code units after `columns(WideEncoding::Utf16)`. Spans end at the column
+/// after their last char, and `span` is that of the primary label.
+pub struct Emitter<'a> {
+    sources: &'a SourceMap,
+    reports: Vec<(FileId, Report)>,
+    encoding: WideEncoding,
+}
+
+#[derive(Serialize)]
+struct JsonSpan<'a> {
+    file: &'a str,
+    line: usize,
+    column: usize,
+    end_line: usize,
+    end_column: usize,
+}
+
+#[derive(Serialize)]
+struct JsonLabel<'a> {
+    span: JsonSpan<'a>,
+    message: &'a str,
+    primary: bool,
+}
+
+#[derive(Serialize)]
+struct JsonSuggestion<'a> {
+    span: JsonSpan<'a>,
+    replacement: &'a str,
+    message: &'a str,
+}
+
+#[derive(Serialize)]
+struct JsonDiagnostic<'a> {
+    severity: &'static str,
+    code: Option<&'static str>,
+    message: &'a str,
+    span: Option<JsonSpan<'a>>,
+    labels: Vec<JsonLabel<'a>>,
+    notes: &'a [String],
+    help: &'a [String],
+    suggestions: Vec<JsonSuggestion<'a>>,
+}
+
+impl<'a> JsonSpan<'a> {
+    fn new(file: &'a str, line: usize, columns: Range<usize>) -> Self {
+        JsonSpan {
+            file,
+            line,
+            column: columns.start,
+            end_line: line,
+            end_column: columns.end,
+        }
+    }
+
+    fn sarif_location(&self) -> Value {
+        json!({
+            "physicalLocation": {
+                "artifactLocation": { "uri": self.file },
+                "region": {
+                    "startLine": self.line,
+                    "startColumn": self.column,
+                    "endLine": self.end_line,
+                    "endColumn": self.end_column,
+                },
+            },
+        })
+    }
+}
+
+impl<'a> Emitter<'a> {
+    pub fn new(sources: &'a SourceMap) -> Self {
+        Emitter {
+            sources,
+            reports: Vec::new(),
+            encoding: WideEncoding::Utf32,
+        }
+    }
+
+    /// Counts columns in `encoding`, e.g. `WideEncoding::Utf16` for editors
+    /// that speak the language server protocol.
+    pub fn columns(mut self, encoding: WideEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Adds a diagnostic located in `file`.
+    pub fn push(&mut self, file: FileId, diagnostic: &impl Diagnostic) {
+        self.push_report(file, diagnostic.report());
+    }
+
+    pub fn push_report(&mut self, file: FileId, report: Report) {
+        self.reports.push((file, report));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reports.is_empty()
+    }
+
+    fn file_name(&self, file: FileId) -> &'a str {
+        self.sources.get(file).map_or("<unknown>", |f| f.name())
+    }
+
+    fn line_index(&self, file: FileId) -> Option<&'a LineIndex> {
+        self.sources.get(file).map(|f| f.line_index())
+    }
+
+    // Columns of files missing from the source map can't be converted, so they are kept in chars.
+    fn label_span(&self, file: FileId, label: &Label) -> JsonSpan<'a> {
+        let columns = self
+            .line_index(file)
+            .and_then(|index| label.columns(index, self.encoding))
+            .unwrap_or(label.col_no..label.col_no + label.len);
+        JsonSpan::new(self.file_name(file), label.line_no, columns)
+    }
+
+    fn suggestion_span(&self, file: FileId, suggestion: &Suggestion) -> JsonSpan<'a> {
+        let columns = self
+            .line_index(file)
+            .and_then(|index| suggestion.columns(index, self.encoding))
+            .unwrap_or(suggestion.col_no..suggestion.col_no + suggestion.len);
+        JsonSpan::new(self.file_name(file), suggestion.line_no, columns)
+    }
+
+    fn json_diagnostics(&self) -> impl Iterator<Item = JsonDiagnostic<'_>> {
+        self.reports.iter().map(|(file, report)| {
+            let file = *file;
+            JsonDiagnostic {
+                severity: report.severity.as_str(),
+                code: report.code,
+                message: &report.message,
+                span: report.primary_label().map(|l| self.label_span(file, l)),
+                labels: report
+                    .labels
+                    .iter()
+                    .map(|l| JsonLabel {
+                        span: self.label_span(file, l),
+                        message: &l.message,
+                        primary: l.primary,
+                    })
+                    .collect(),
+                notes: &report.notes,
+                help: &report.help,
+                suggestions: report
+                    .suggestions
+                    .iter()
+                    .map(|s| JsonSuggestion {
+                        span: self.suggestion_span(file, s),
+                        replacement: &s.replacement,
+                        message: &s.message,
+                    })
+                    .collect(),
+            }
+        })
+    }
+
+    /// One JSON object per line for each diagnostic, in the order they were pushed.
+    pub fn to_json_lines(&self) -> String {
+        self.json_diagnostics()
+            .map(|d| serde_json::to_string(&d).expect("diagnostics always serialize") + "\n")
+            .collect()
+    }
+
+    /// A SARIF 2.1.0 log with a single run holding every diagnostic. Secondary
+    /// labels become related locations, suggestions become fixes and notes and
+    /// help are kept in the result's properties.
+    pub fn to_sarif(&self) -> String {
+        let mut rules = Vec::new();
+        let results = self
+            .json_diagnostics()
+            .map(|d| {
+                let code = d.code.unwrap_or("unknown");
+                if !rules.contains(&code) {
+                    rules.push(code);
+                }
+                let level = match d.severity {
+                    s if s == Severity::Warning.as_str() => "warning",
+                    _ => "error",
+                };
+                let related = d
+                    .labels
+                    .iter()
+                    .filter(|l| !l.primary)
+                    .enumerate()
+                    .map(|(id, l)| {
+                        let mut location = l.span.sarif_location();
+                        location["id"] = json!(id);
+                        location["message"] = json!({ "text": l.message });
+                        location
+                    })
+                    .collect::<Vec<_>>();
+                let fixes = d
+                    .suggestions
+                    .iter()
+                    .map(|s| {
+                        let region = &s.span.sarif_location()["physicalLocation"]["region"];
+                        json!({
+                            "description": { "text": s.message },
+                            "artifactChanges": [{
+                                "artifactLocation": { "uri": s.span.file },
+                                "replacements": [{
+                                    "deletedRegion": region,
+                                    "insertedContent": { "text": s.replacement },
+                                }],
+                            }],
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                json!({
+                    "ruleId": code,
+                    "level": level,
+                    "message": { "text": d.message },
+                    "locations": d.span.iter().map(JsonSpan::sarif_location).collect::<Vec<_>>(),
+                    "relatedLocations": related,
+                    "fixes": fixes,
+                    "properties": { "notes": d.notes, "help": d.help },
+                })
+            })
+            .collect::<Vec<_>>();
+        let log = json!({
+            "$schema": SARIF_SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+                    },
+                },
+                "columnKind": match self.encoding {
+                    WideEncoding::Utf16 => "utf16CodeUnits",
+                    WideEncoding::Utf32 => "unicodeCodePoints",
+                },
+                "results": results,
+            }],
+        });
+        serde_json::to_string_pretty(&log).expect("diagnostics always serialize")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::Lexer;
+
+    fn emitter(sources: &SourceMap) -> Emitter<'_> {
+        let (file, source) = sources.files().next().unwrap();
+        let (_, errors, _) = Lexer::for_file(file).lex_recovering(source.source());
+        let mut emitter = Emitter::new(sources);
+        for error in &errors {
+            emitter.push(file, error);
+        }
+        emitter
+    }
+
+    #[test]
+    fn emits_json_lines() {
+        let mut sources = SourceMap::new();
+        sources.add_file("test.t", "var $\nsprint(\"\\q\")\n");
+        let lines = emitter(&sources).to_json_lines();
+        let lines = lines.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            r#"{"severity":"error","code":"L0013","message":"unexpected character \"$\"","span":{"file":"test.t","line":1,"column":5,"end_line":1,"end_column":6},"labels":[{"span":{"file":"test.t","line":1,"column":5,"end_line":1,"end_column":6},"message":"not valid here","primary":true}],"notes":[],"help":[],"suggestions":[]}"#
+        );
+        let escape: Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(escape["code"], "L0004");
+        assert_eq!(escape["suggestions"][0]["replacement"], "\\\\");
+        assert_eq!(escape["suggestions"][0]["span"]["column"], 9);
+    }
+
+    #[test]
+    fn emits_sarif() {
+        let mut sources = SourceMap::new();
+        let file = sources.add_file("src/test.t", "x : 1\ny : \"\\q\"\n");
+        let mut emitter = emitter(&sources);
+        emitter.push_report(
+            file,
+            Report::warning("shadowed")
+                .with_code("W9999")
+                .with_label(Label::primary(2, 1, 1, "shadows x"))
+                .with_label(Label::secondary(1, 1, 1, "x declared here"))
+                .with_note("a note"),
+        );
+        let sarif: Value = serde_json::from_str(&emitter.to_sarif()).unwrap();
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        assert_eq!(
+            run["tool"]["driver"]["rules"],
+            json!([{ "id": "L0004" }, { "id": "W9999" }])
+        );
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results[0]["level"], "error");
+        assert_eq!(
+            results[0]["locations"][0]["physicalLocation"],
+            json!({
+                "artifactLocation": { "uri": "src/test.t" },
+                "region": { "startLine": 2, "startColumn": 6, "endLine": 2, "endColumn": 8 },
+            })
+        );
+        let fix = &results[0]["fixes"][0]["artifactChanges"][0]["replacements"][0];
+        assert_eq!(fix["deletedRegion"]["endColumn"], 7);
+        assert_eq!(fix["insertedContent"]["text"], "\\\\");
+        assert_eq!(results[1]["level"], "warning");
+        assert_eq!(
+            results[1]["relatedLocations"][0]["message"]["text"],
+            "x declared here"
+        );
+        assert_eq!(results[1]["properties"]["notes"], json!(["a note"]));
+        assert_eq!(run["columnKind"], "unicodeCodePoints");
+    }
+
+    #[test]
+    fn converts_columns_to_utf16() {
+        let mut sources = SourceMap::new();
+        sources.add_file("test.t", "s : \"😀\" $\n");
+        let json: Value = serde_json::from_str(&emitter(&sources).to_json_lines()).unwrap();
+        assert_eq!(
+            (&json["span"]["column"], &json["span"]["end_column"]),
+            (&json!(9), &json!(10))
+        );
+        let emitter = emitter(&sources).columns(WideEncoding::Utf16);
+        let json: Value = serde_json::from_str(&emitter.to_json_lines()).unwrap();
+        assert_eq!(
+            (&json["span"]["column"], &json["span"]["end_column"]),
+            (&json!(10), &json!(11))
+        );
+        let sarif: Value = serde_json::from_str(&emitter.to_sarif()).unwrap();
+        assert_eq!(sarif["runs"][0]["columnKind"], "utf16CodeUnits");
+    }
+}
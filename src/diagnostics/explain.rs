@@ -0,0 +1,361 @@
+use std::fmt;
+
+/// The long-form explanation of a diagnostic code, as printed by
+/// `desolation explain <CODE>`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Explanation {
+    pub code: &'static str,
+    /// The message the diagnostic is usually reported with.
+    pub title: &'static str,
+    pub description: &'static str,
+    /// Code that triggers the diagnostic. Empty if there's no such code.
+    pub bad: &'static str,
+    /// `bad`, fixed.
+    pub good: &'static str,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}: {}\n", self.code, self.title)?;
+        writeln!(f, "{}", self.description)?;
+        if !self.bad.is_empty() {
+            writeln!(f, "\nErroneous code example:\n\n```\n{}\n```", self.bad)?;
+        }
+        if !self.good.is_empty() {
+            writeln!(f, "\nCorrected:\n\n```\n{}\n```", self.good)?;
+        }
+        Ok(())
+    }
+}
+
+/// Looks up the explanation of a code such as `L0004`, ignoring case.
+pub fn explain(code: &str) -> Option<&'static Explanation> {
+    EXPLANATIONS
+        .iter()
+        .find(|e| e.code.eq_ignore_ascii_case(code))
+}
+
+/// Every explained code, in order.
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "L0001",
+        title: "unexpected character",
+        description: "A character that can't start any token was found.
+
+This code is no longer emitted: unexpected characters are reported as L0013.",
+        bad: "",
+        good: "var x",
+    },
+    Explanation {
+        code: "L0002",
+        title: "unterminated string literal",
+        description: "A string literal was opened with `\"` but the file ended before the closing `\"`.
+
+Strings may span lines, so a missing quote is often reported far below the place it is missing from.",
+        bad: "sprint(\"Table of squares:\\n)",
+        good: "sprint(\"Table of squares:\\n\")",
+    },
+    Explanation {
+        code: "L0003",
+        title: "invalid character literal",
+        description: "A character literal must hold exactly one character or escape sequence between single quotes. Use a string literal for anything longer.",
+        bad: "c : 'ab'",
+        good: "c : 'a'",
+    },
+    Explanation {
+        code: "L0004",
+        title: "unknown escape sequence",
+        description: "A backslash in a string or character literal starts an escape sequence, and only \\n, \\t, \\\\, \\\", \\', \\0, \\x7F-style hex escapes and \\u{10FFFF}-style unicode escapes are supported.
+
+To write a backslash itself, escape it as `\\\\`.",
+        bad: "sprint(\"C:\\dir\")",
+        good: "sprint(\"C:\\\\dir\")",
+    },
+    Explanation {
+        code: "L0005",
+        title: "invalid hex escape",
+        description: "A `\\x` escape takes exactly two hex digits and can only name an ASCII character, so its value must be no greater than \\x7F. Use a `\\u{...}` escape for other characters.",
+        bad: "sprint(\"\\x80\")",
+        good: "sprint(\"\\u{80}\")",
+    },
+    Explanation {
+        code: "L0006",
+        title: "invalid unicode escape",
+        description: "A `\\u` escape is written `\\u{...}` with 1 to 6 hex digits, and must name a unicode scalar value: at most 10FFFF and not a surrogate (D800 to DFFF).",
+        bad: "sprint(\"\\u{110000}\")",
+        good: "sprint(\"\\u{10FFFF}\")",
+    },
+    Explanation {
+        code: "L0007",
+        title: "invalid integer literal",
+        description: "An integer literal is a run of decimal digits, or of hex, binary or octal digits after a `0x`, `0b` or `0o` prefix. Digits may be separated by `_`. Letters right after a number are taken as part of it, so an identifier can't start with a digit.",
+        bad: "i : 12ab",
+        good: "i : 0x12ab",
+    },
+    Explanation {
+        code: "L0008",
+        title: "integer literal is too large",
+        description: "Integers are 64-bit signed values, so the largest literal is 9223372036854775807. The literal 9223372036854775808 is only allowed so that it can be negated to the smallest integer.",
+        bad: "i : 9223372036854775809",
+        good: "i : 9223372036854775807",
+    },
+    Explanation {
+        code: "L0009",
+        title: "invalid identifier",
+        description: "Identifiers start with a letter or `_` and continue with letters, digits and `_`, as defined by Unicode Standard Annex #31.
+
+This code is no longer emitted: characters that can't appear in identifiers are reported as L0013.",
+        bad: "",
+        good: "var x2",
+    },
+    Explanation {
+        code: "L0010",
+        title: "unterminated block comment",
+        description: "A block comment opened with `#[` was not closed with `]#` before the end of the file. Block comments nest, so every `#[` inside a comment needs its own `]#`.",
+        bad: "#[ outer #[ inner ]#\nvar x",
+        good: "#[ outer #[ inner ]# ]#\nvar x",
+    },
+    Explanation {
+        code: "L0011",
+        title: "unexpected end of file",
+        description: "The file ended in the middle of a token, for example right after the backslash of an escape sequence in a character literal.",
+        bad: "c : '\\",
+        good: "c : '\\\\'",
+    },
+    Explanation {
+        code: "L0012",
+        title: "unexpected end of line",
+        description: "A line ended where the lexer needed more of it.
+
+This code is no longer emitted: it used to be reported for a comment on the last line of a file without a trailing newline, which is now allowed.",
+        bad: "",
+        good: "x : 1 # no newline after this comment",
+    },
+    Explanation {
+        code: "L0013",
+        title: "unexpected character",
+        description: "A character that can't start any token was found outside of a string, character literal or comment.",
+        bad: "var $x",
+        good: "var x",
+    },
+    Explanation {
+        code: "L0014",
+        title: "unknown lexer error",
+        description: "The lexer failed in a way it couldn't describe. This is a bug in the lexer; please report it along with the code that caused it.",
+        bad: "",
+        good: "",
+    },
+    Explanation {
+        code: "W0001",
+        title: "identifier mixes scripts",
+        description: "No single script, such as Latin or Cyrillic, covers every character of the identifier. Letters from different scripts can look identical, so the name may be mistaken for a different one. Spell the identifier in one script.",
+        bad: "var pаypal # the second letter is a Cyrillic а",
+        good: "var paypal",
+    },
+    Explanation {
+        code: "P0001",
+        title: "unexpected token",
+        description: "The parser found a token that can't appear at this point of the program. The report says which token was expected instead.",
+        bad: "fun sq(n {\n    return .n * .n\n}",
+        good: "fun sq(n) {\n    return .n * .n\n}",
+    },
+    Explanation {
+        code: "P0002",
+        title: "unexpected end of file",
+        description: "The file ended while the parser was still in the middle of a declaration, usually because of a missing closing brace.",
+        bad: "fun sq(n) {\n    return .n * .n",
+        good: "fun sq(n) {\n    return .n * .n\n}",
+    },
+    Explanation {
+        code: "P0003",
+        title: "expected a construct",
+        description: "The parser expected an expression, declaration or other construct, but found a token that can't start one. Only `var` and `fun` declarations may appear outside a function.",
+        bad: "var x\nx : 1",
+        good: "var x : 1",
+    },
+    Explanation {
+        code: "P0004",
+        title: "global variables and functions in the same file",
+        description: "Files used to declare either only global variables or only functions.
+
+This code is no longer emitted: global variables and functions may be declared in the same file, in any order.",
+        bad: "",
+        good: "",
+    },
+    Explanation {
+        code: "P0005",
+        title: "integer literal too large",
+        description: "Integers are 64-bit and signed, so a literal can be at most 9223372036854775807. The one exception is -9223372036854775808, written as a negated literal.",
+        bad: "var x : 9223372036854775808",
+        good: "var x : -9223372036854775808",
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Diagnostic;
+    use crate::lex::{Lexer, LexerError, LexerWarning, TokenType};
+    use crate::parser::{ParseError, Parser};
+    use crate::source::Span;
+
+    // Codes whose `bad` example can't be checked against the lexer.
+    const NOT_EMITTED: [&str; 1] = ["L0014"];
+
+    // Codes that no diagnostic reports anymore, kept so old ones still explain.
+    const RETIRED: [&str; 4] = ["L0001", "L0009", "L0012", "P0004"];
+
+    fn lexer_codes(source: &str) -> Vec<&'static str> {
+        let (_, errors, warnings) = Lexer::new().lex_recovering(source);
+        errors
+            .iter()
+            .map(|e| e.code())
+            .chain(warnings.iter().map(|w| w.code()))
+            .collect()
+    }
+
+    #[test]
+    fn every_variant_has_an_explanation() {
+        let token = TokenType::Eof.at(Span::default(), 1, 1);
+        let lexer_errors = [
+            LexerError::InvalidStringLiteral(1, 1),
+            LexerError::InvalidCharacterLiteral(1, 1),
+            LexerError::UnknownEscape('q', 1, 1),
+            LexerError::InvalidHexEscape(String::new(), 1, 1),
+            LexerError::InvalidUnicodeEscape(String::new(), 1, 1),
+            LexerError::InvalidIntegerLiteral(String::new(), 1, 1),
+            LexerError::IntegerOverflow(String::new(), 1, 1),
+            LexerError::InvalidComment(1, 1),
+            LexerError::InvalidEOF(1, 1),
+            LexerError::UnknownToken(token.clone()),
+            LexerError::Unknown(1, 1),
+        ];
+        // Adding a variant breaks these matches until it is listed above too.
+        for error in &lexer_errors {
+            match error {
+                LexerError::InvalidStringLiteral(..)
+                | LexerError::InvalidCharacterLiteral(..)
+                | LexerError::UnknownEscape(..)
+                | LexerError::InvalidHexEscape(..)
+                | LexerError::InvalidUnicodeEscape(..)
+                | LexerError::InvalidIntegerLiteral(..)
+                | LexerError::IntegerOverflow(..)
+                | LexerError::InvalidComment(..)
+                | LexerError::InvalidEOF(..)
+                | LexerError::UnknownToken(..)
+                | LexerError::Unknown(..) => {}
+            }
+        }
+        let warnings = [LexerWarning::MixedScriptIdentifier(
+            String::new(),
+            1,
+            1,
+            String::new(),
+        )];
+        match &warnings[0] {
+            LexerWarning::MixedScriptIdentifier(..) => {}
+        }
+        let parse_errors = [
+            ParseError::unexpected(
+                TokenType::NL.kind(),
+                &TokenType::NL.at(Span::default(), 1, 1),
+            ),
+            ParseError::unexpected(TokenType::NL.kind(), &token),
+            ParseError::expected("an expression", &token),
+            ParseError::IntegerOverflow {
+                found: Box::new(token.clone()),
+            },
+        ];
+        for error in &parse_errors {
+            match error {
+                ParseError::UnexpectedToken { .. }
+                | ParseError::UnexpectedEOF { .. }
+                | ParseError::Expected { .. }
+                | ParseError::IntegerOverflow { .. } => {}
+            }
+        }
+
+        let mut codes = lexer_errors
+            .iter()
+            .map(|e| e.code())
+            .chain(warnings.iter().map(|w| w.code()))
+            .chain(parse_errors.iter().map(|e| e.code()))
+            .collect::<Vec<_>>();
+        for code in &codes {
+            assert!(explain(code).is_some(), "{} has no explanation", code);
+        }
+        codes.sort_unstable();
+        codes.dedup();
+        for code in RETIRED {
+            assert!(!codes.contains(&code), "{} is retired", code);
+        }
+        assert_eq!(
+            codes.len() + RETIRED.len(),
+            EXPLANATIONS.len(),
+            "codes are shared or unused"
+        );
+    }
+
+    #[test]
+    fn lexer_examples_match_their_codes() {
+        for explanation in EXPLANATIONS {
+            let code = explanation.code;
+            if code.starts_with('P') {
+                continue;
+            }
+            assert!(
+                lexer_codes(explanation.good).is_empty(),
+                "{} good example",
+                code
+            );
+            if !NOT_EMITTED.contains(&code) && !RETIRED.contains(&code) {
+                assert!(
+                    lexer_codes(explanation.bad).contains(&code),
+                    "{} bad example gives {:?}",
+                    code,
+                    lexer_codes(explanation.bad)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parser_examples_match_their_codes() {
+        let parse = |source| Parser::new(Lexer::new().lex_str(source).unwrap()).parse();
+        for explanation in EXPLANATIONS {
+            let code = explanation.code;
+            if !code.starts_with('P') || RETIRED.contains(&code) {
+                continue;
+            }
+            assert!(parse(explanation.good).is_ok(), "{} good example", code);
+            let err = parse(explanation.bad).unwrap_err();
+            let err = err.downcast_ref::<ParseError>().unwrap();
+            assert_eq!(err.code(), code, "{} bad example", code);
+        }
+    }
+
+    #[test]
+    fn explains_codes_in_any_case() {
+        let explanation = explain("l0003").unwrap();
+        assert_eq!(
+            explanation.to_string(),
+            "L0003: invalid character literal
+
+A character literal must hold exactly one character or escape sequence between single quotes. Use a string literal for anything longer.
+
+Erroneous code example:
+
+```
+c : 'ab'
+```
+
+Corrected:
+
+```
+c : 'a'
+```
+"
+        );
+        assert!(explain("E9999").is_none());
+    }
+}
@@ -0,0 +1,217 @@
+pub(crate) mod emit;
+pub(crate) mod explain;
+pub(crate) mod render;
+
+use crate::lex::Token;
+use crate::source::{LineCol, LineIndex, WideEncoding};
+use std::ops::Range;
+
+pub use emit::Emitter;
+pub use explain::{explain, Explanation, EXPLANATIONS};
+pub use render::Renderer;
+
+/// How serious a report is.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// Marks a stretch of a source line and says something about it.
+///
+/// Positions are 1-based and counted in chars, like those of tokens. A label
+/// never spans more than one line; anything past the end of its line is cut off.
+/// `columns` converts them to other units through the `LineIndex` of the file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Label {
+    pub line_no: usize,
+    pub col_no: usize,
+    /// Length in chars. Zero-length labels are drawn as a single mark.
+    pub len: usize,
+    pub message: String,
+    /// Primary labels point at the problem itself, secondary ones at related code.
+    pub primary: bool,
+}
+
+impl Label {
+    pub fn primary(line_no: usize, col_no: usize, len: usize, message: impl Into<String>) -> Self {
+        Label {
+            line_no,
+            col_no,
+            len,
+            message: message.into(),
+            primary: true,
+        }
+    }
+
+    pub fn secondary(
+        line_no: usize,
+        col_no: usize,
+        len: usize,
+        message: impl Into<String>,
+    ) -> Self {
+        Label {
+            primary: false,
+            ..Label::primary(line_no, col_no, len, message)
+        }
+    }
+
+    /// A primary label covering `token`.
+    pub fn token(token: &Token, message: impl Into<String>) -> Self {
+        let len = token.text().chars().count();
+        Label::primary(token.line_no(), token.col_no(), len, message)
+    }
+
+    /// The columns the label covers, in `encoding`, cut off at the end of its
+    /// line. `None` if `index` has no such line.
+    pub fn columns(&self, index: &LineIndex, encoding: WideEncoding) -> Option<Range<usize>> {
+        columns(index, encoding, self.line_no, self.col_no, self.len)
+    }
+}
+
+/// A proposed edit that fixes the problem: replace `len` chars at the
+/// position with `replacement`. A zero `len` inserts.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Suggestion {
+    pub line_no: usize,
+    pub col_no: usize,
+    pub len: usize,
+    pub replacement: String,
+    pub message: String,
+}
+
+impl Suggestion {
+    pub fn new(
+        line_no: usize,
+        col_no: usize,
+        len: usize,
+        replacement: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Suggestion {
+            line_no,
+            col_no,
+            len,
+            replacement: replacement.into(),
+            message: message.into(),
+        }
+    }
+
+    /// The columns the suggestion replaces, in `encoding`, like `Label::columns`.
+    pub fn columns(&self, index: &LineIndex, encoding: WideEncoding) -> Option<Range<usize>> {
+        columns(index, encoding, self.line_no, self.col_no, self.len)
+    }
+}
+
+// Converts `len` chars from `col_no` of `line_no` to columns in `encoding`,
+// clamping both ends to the line.
+fn columns(
+    index: &LineIndex,
+    encoding: WideEncoding,
+    line_no: usize,
+    col_no: usize,
+    len: usize,
+) -> Option<Range<usize>> {
+    let line = index.line_range(line_no)?;
+    let end = LineCol::new(line_no, line.len() + 1);
+    let last = index.to_wide(WideEncoding::Utf32, end)?.col;
+    let column = |col: usize| {
+        let utf8 = index.to_utf8(
+            WideEncoding::Utf32,
+            LineCol::new(line_no, col.clamp(1, last)),
+        )?;
+        Some(index.to_wide(encoding, utf8)?.col)
+    };
+    Some(column(col_no)?..column(col_no + len)?)
+}
+
+/// Everything needed to explain a problem to the user, rendered by `Renderer`
+/// for people and serialized by `Emitter` for tools.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Report {
+    pub severity: Severity,
+    /// The stable code of the problem, e.g. `L0002`.
+    pub code: Option<&'static str>,
+    /// A short description of the problem, without its location.
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl Report {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Report {
+            severity,
+            code: None,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: Vec::new(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Report::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Report::new(Severity::Warning, message)
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    pub fn with_suggestion(mut self, suggestion: Suggestion) -> Self {
+        self.suggestions.push(suggestion);
+        self
+    }
+
+    /// The label the report is located at: the first primary one, if any.
+    pub fn primary_label(&self) -> Option<&Label> {
+        self.labels
+            .iter()
+            .find(|l| l.primary)
+            .or_else(|| self.labels.first())
+    }
+}
+
+/// Implemented by every error and warning the compiler reports to the user.
+///
+/// Codes are stable: CI annotations and suppressions refer to them, so a code
+/// is never reused for a different problem. Lexer errors use `L`, parser
+/// errors `P` and lints `W`. Every code is explained in `EXPLANATIONS`.
+pub trait Diagnostic {
+    fn code(&self) -> &'static str;
+
+    /// The report, which carries `code`.
+    fn report(&self) -> Report;
+}
@@ -0,0 +1,272 @@
+use crate::diagnostics::{Label, Report, Severity};
+use crate::source::{LineCol, LineIndex, SourceFile, WideEncoding};
+use std::fmt::Write;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Renders reports in the style of rustc:
+///
+/// ```text
+/// error[L0010]: unterminated block comment
+///  --> examples/sq.t:2:5
+///   |
+/// 2 | x : #[ 1 #[ 2 ]#
+///   |     ^^ comment starts here
+///   |
+///   = note: block comments nest, so each one opened inside needs closing too
+/// ```
+///
+/// Colour is off unless turned on with `colour`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Renderer {
+    colour: bool,
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Renderer::default()
+    }
+
+    /// Whether to colour the output with ANSI escape codes.
+    pub fn colour(mut self, colour: bool) -> Self {
+        self.colour = colour;
+        self
+    }
+
+    // Wraps `text` in the given style when colouring.
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.colour {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn severity_style(severity: Severity) -> &'static str {
+        match severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        }
+    }
+
+    /// Renders `report`, whose labels point into `file`. The result ends with a newline.
+    pub fn render(&self, report: &Report, file: &SourceFile) -> String {
+        let style = Renderer::severity_style(report.severity);
+        let mut out = String::new();
+        let severity = match report.code {
+            Some(code) => format!("{}[{}]", report.severity.as_str(), code),
+            None => report.severity.as_str().to_string(),
+        };
+        let _ = writeln!(
+            out,
+            "{}{}",
+            self.paint(style, &severity),
+            self.paint(BOLD, &format!(": {}", report.message))
+        );
+
+        let mut lines = report.labels.iter().map(|l| l.line_no).collect::<Vec<_>>();
+        lines.sort_unstable();
+        lines.dedup();
+        let width = lines.last().map_or(0, |n| n.to_string().len());
+        let pad = " ".repeat(width);
+        let gutter = self.paint(BLUE, "|");
+
+        if let Some(label) = report.primary_label() {
+            let _ = writeln!(
+                out,
+                "{}{} {}:{}:{}",
+                pad,
+                self.paint(BLUE, "-->"),
+                file.name(),
+                label.line_no,
+                label.col_no
+            );
+            let _ = writeln!(out, "{} {}", pad, gutter);
+        }
+        let mut previous = None;
+        for &line_no in &lines {
+            if previous.is_some_and(|p: usize| line_no > p + 1) {
+                let _ = writeln!(out, "{}", self.paint(BLUE, "..."));
+            }
+            previous = Some(line_no);
+            let text = file.line(line_no).unwrap_or("");
+            let _ = writeln!(
+                out,
+                "{} {} {}",
+                self.paint(BLUE, &format!("{:>width$}", line_no)),
+                gutter,
+                text
+            );
+            let mut labels = report
+                .labels
+                .iter()
+                .filter(|l| l.line_no == line_no)
+                .collect::<Vec<_>>();
+            labels.sort_by_key(|l| l.col_no);
+            for label in labels {
+                let _ = writeln!(
+                    out,
+                    "{} {} {}",
+                    pad,
+                    gutter,
+                    self.underline(label, file.line_index(), text, style)
+                );
+            }
+        }
+
+        let suggestions = report
+            .suggestions
+            .iter()
+            .map(|s| format!("{}: `{}`", s.message, s.replacement))
+            .collect::<Vec<_>>();
+        let notes = report
+            .notes
+            .iter()
+            .map(|n| ("note", n))
+            .chain(report.help.iter().map(|h| ("help", h)))
+            .chain(suggestions.iter().map(|s| ("help", s)))
+            .collect::<Vec<_>>();
+        if !notes.is_empty() {
+            if !lines.is_empty() {
+                let _ = writeln!(out, "{} {}", pad, gutter);
+            }
+            for (kind, text) in notes {
+                let _ = writeln!(
+                    out,
+                    "{} {} {}",
+                    pad,
+                    self.paint(BLUE, "="),
+                    self.paint(BOLD, &format!("{}:", kind)) + " " + text
+                );
+            }
+        }
+        out
+    }
+
+    // The marks under `label` followed by its message. The indent copies any
+    // tabs from `line` so the marks line up however tabs are displayed, and a
+    // label past the end of the line is indented past it with spaces.
+    fn underline(&self, label: &Label, index: &LineIndex, line: &str, style: &str) -> String {
+        let columns = label.columns(index, WideEncoding::Utf32).unwrap_or(1..1);
+        let start = index
+            .to_utf8(
+                WideEncoding::Utf32,
+                LineCol::new(label.line_no, columns.start),
+            )
+            .map_or(0, |line_col| line_col.col - 1);
+        let mut indent = line[..start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        indent.extend(std::iter::repeat_n(
+            ' ',
+            label.col_no.saturating_sub(columns.start),
+        ));
+        let len = columns.len().max(1);
+        let (mark, style) = if label.primary {
+            ("^", style)
+        } else {
+            ("-", BLUE)
+        };
+        let marks = self.paint(style, &mark.repeat(len));
+        if label.message.is_empty() {
+            format!("{}{}", indent, marks)
+        } else {
+            format!("{}{} {}", indent, marks, self.paint(style, &label.message))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::SourceMap;
+
+    fn file(source: &str) -> SourceMap {
+        let mut map = SourceMap::new();
+        map.add_file("test.t", source);
+        map
+    }
+
+    #[test]
+    fn renders_labels_notes_and_help() {
+        let map = file("fun f(a, a) {\n\treturn a\n}\n");
+        let file = map.files().next().unwrap().1;
+        let report = Report::error("parameter `a` is declared twice")
+            .with_label(Label::primary(1, 10, 1, "declared again here"))
+            .with_label(Label::secondary(1, 7, 1, "first declared here"))
+            .with_label(Label::secondary(2, 9, 1, "used here"))
+            .with_note("parameters must have distinct names")
+            .with_help("rename one of them");
+        assert_eq!(
+            Renderer::new().render(&report, file),
+            "error: parameter `a` is declared twice
+ --> test.t:1:10
+  |
+1 | fun f(a, a) {
+  |       - first declared here
+  |          ^ declared again here
+2 | \treturn a
+  | \t       - used here
+  |
+  = note: parameters must have distinct names
+  = help: rename one of them
+"
+        );
+    }
+
+    #[test]
+    fn elides_lines_between_labels_and_clamps_marks() {
+        let map = file("a\nb\nc\nd");
+        let file = map.files().next().unwrap().1;
+        let report = Report::warning("unused")
+            .with_label(Label::primary(1, 1, 10, ""))
+            .with_label(Label::secondary(4, 2, 0, "end"));
+        assert_eq!(
+            Renderer::new().render(&report, file),
+            "warning: unused
+ --> test.t:1:1
+  |
+1 | a
+  | ^
+...
+4 | d
+  |  - end
+"
+        );
+    }
+
+    #[test]
+    fn lines_up_marks_after_wide_chars() {
+        let map = file("s : \"😀é\" $\n");
+        let file = map.files().next().unwrap().1;
+        let report = Report::error("bad")
+            .with_label(Label::primary(1, 10, 1, "here"))
+            .with_label(Label::secondary(1, 13, 2, "past the end"));
+        assert_eq!(
+            Renderer::new().render(&report, file),
+            "error: bad
+ --> test.t:1:10
+  |
+1 | s : \"😀é\" $
+  |          ^ here
+  |             - past the end
+"
+        );
+    }
+
+    #[test]
+    fn colours_only_when_asked() {
+        let map = file("x");
+        let file = map.files().next().unwrap().1;
+        let report = Report::error("bad").with_label(Label::primary(1, 1, 1, "here"));
+        assert!(!Renderer::new().render(&report, file).contains('\x1b'));
+        let coloured = Renderer::new().colour(true).render(&report, file);
+        assert!(coloured.starts_with("\x1b[1;31merror\x1b[0m"));
+        assert!(coloured.contains("\x1b[1;31m^\x1b[0m"));
+    }
+}
@@ -0,0 +1,193 @@
+use crate::lex::types::KeywordToken;
+use std::collections::HashMap;
+
+/// Describes the dialect the lexer accepts.
+///
+/// `LexerConfig::strict()` (the default) is the language as it has always been
+/// lexed, plus `_` in identifiers, nested `#[ … ]#` block comments and `##` doc
+/// comments; `LexerConfig::extended()` is the variant with extra keywords and
+/// C-style comments. Either can be adjusted field by field.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LexerConfig {
+    /// Maps the spelling of every keyword to its token.
+    pub keywords: HashMap<String, KeywordToken>,
+    /// Strings that start a comment running to the end of the line or file.
+    pub line_comments: Vec<String>,
+    /// Pairs of strings opening and closing a block comment. Block comments nest.
+    pub block_comments: Vec<(String, String)>,
+    /// Strings that start a doc comment. Doc comments run to the end of the
+    /// line and, unlike other comments, are lexed as `DocComment` tokens.
+    pub doc_comments: Vec<String>,
+    /// Whether identifiers may start with and contain `_`, as UAX #31 allows.
+    pub underscore_in_identifiers: bool,
+    /// Whether newlines produce NL tokens. When false they are skipped like any other whitespace.
+    pub significant_newlines: bool,
+}
+
+const STRICT_KEYWORDS: [KeywordToken; 7] = [
+    KeywordToken::Var,
+    KeywordToken::Fun,
+    KeywordToken::If,
+    KeywordToken::Else,
+    KeywordToken::Until,
+    KeywordToken::Loop,
+    KeywordToken::Return,
+];
+
+const EXTENDED_KEYWORDS: [KeywordToken; 2] = [KeywordToken::Const, KeywordToken::Import];
+
+impl LexerConfig {
+    pub fn strict() -> Self {
+        LexerConfig {
+            keywords: STRICT_KEYWORDS
+                .iter()
+                .map(|k| (k.as_str().to_string(), *k))
+                .collect(),
+            line_comments: vec!["#".to_string()],
+            block_comments: vec![("#[".to_string(), "]#".to_string())],
+            doc_comments: vec!["##".to_string()],
+            underscore_in_identifiers: true,
+            significant_newlines: true,
+        }
+    }
+
+    pub fn extended() -> Self {
+        let mut config = LexerConfig::strict();
+        config.keywords.extend(
+            EXTENDED_KEYWORDS
+                .iter()
+                .map(|k| (k.as_str().to_string(), *k)),
+        );
+        config.line_comments.push("//".to_string());
+        config
+            .block_comments
+            .push(("/*".to_string(), "*/".to_string()));
+        config.doc_comments.push("///".to_string());
+        config
+    }
+
+    pub fn keyword(&self, s: &str) -> Option<KeywordToken> {
+        self.keywords.get(s).copied()
+    }
+}
+
+impl Default for LexerConfig {
+    fn default() -> Self {
+        LexerConfig::strict()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::token::{TokenKind, TokenType};
+    use crate::lex::types::SyntaxToken;
+    use crate::lex::Lexer;
+
+    fn kinds(config: LexerConfig, source: &str) -> Vec<TokenKind> {
+        Lexer::with_config(config)
+            .lex_str(source)
+            .unwrap()
+            .tokens
+            .iter()
+            .map(|t| t.kind())
+            .collect()
+    }
+
+    #[test]
+    fn strict_matches_the_original_language() {
+        let config = LexerConfig::strict();
+        assert_eq!(config, LexerConfig::default());
+        for keyword in STRICT_KEYWORDS {
+            assert_eq!(KeywordToken::from_str(keyword.as_str()), Some(keyword));
+            assert_eq!(config.keyword(keyword.as_str()), Some(keyword));
+        }
+        assert_eq!(
+            kinds(config.clone(), "const import # note\na // b\n"),
+            vec![
+                TokenKind::Identifier,
+                TokenKind::Identifier,
+                TokenKind::NL,
+                TokenKind::Identifier,
+                TokenKind::Syntax(SyntaxToken::Slash),
+                TokenKind::Syntax(SyntaxToken::Slash),
+                TokenKind::Identifier,
+                TokenKind::NL,
+                TokenKind::Eof,
+            ]
+        );
+        assert_eq!(
+            kinds(config.clone(), "my_var"),
+            vec![TokenKind::Identifier, TokenKind::Eof]
+        );
+        assert_eq!(
+            kinds(config, "#[ a #[ b ]# ]# x ## doc\n/* y */"),
+            vec![
+                TokenKind::Identifier,
+                TokenKind::DocComment,
+                TokenKind::NL,
+                TokenKind::Syntax(SyntaxToken::Slash),
+                TokenKind::Syntax(SyntaxToken::Times),
+                TokenKind::Identifier,
+                TokenKind::Syntax(SyntaxToken::Times),
+                TokenKind::Syntax(SyntaxToken::Slash),
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn underscores_can_be_disallowed() {
+        let config = LexerConfig {
+            underscore_in_identifiers: false,
+            ..LexerConfig::strict()
+        };
+        assert!(Lexer::with_config(config.clone())
+            .lex_str("my_var")
+            .is_err());
+        assert!(Lexer::with_config(config).lex_str("_x").is_err());
+    }
+
+    #[test]
+    fn extended_adds_keywords_and_comments() {
+        let tokens = Lexer::with_config(LexerConfig::extended())
+            .lex_str("const _max_len : 10 // limit\nimport io # io\n")
+            .unwrap()
+            .tokens;
+        let types = tokens
+            .iter()
+            .map(|t| t.token_type().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(types[0], TokenType::Keyword(KeywordToken::Const));
+        assert_eq!(types[1].text(), "_max_len");
+        assert_eq!(types[4], TokenType::NL);
+        assert_eq!(types[5], TokenType::Keyword(KeywordToken::Import));
+        assert_eq!(types[7], TokenType::NL);
+        assert_eq!(types.len(), 9);
+    }
+
+    #[test]
+    fn newlines_can_be_insignificant() {
+        let config = LexerConfig {
+            significant_newlines: false,
+            ..LexerConfig::strict()
+        };
+        assert_eq!(
+            kinds(config.clone(), "var\n\nx\n"),
+            vec![
+                TokenKind::Keyword(KeywordToken::Var),
+                TokenKind::Identifier,
+                TokenKind::Eof,
+            ]
+        );
+        let source = format!("var{}x", "# comment\n".repeat(100_000));
+        assert_eq!(
+            kinds(config, &source),
+            vec![
+                TokenKind::Keyword(KeywordToken::Var),
+                TokenKind::Identifier,
+                TokenKind::Eof,
+            ]
+        );
+    }
+}
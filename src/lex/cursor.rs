@@ -1,38 +1,110 @@
-use std::str::Chars;
+use crate::source::{LineCol, LineIndex, WideEncoding};
+use std::collections::VecDeque;
 
 // Taken from the rustc compiler.
-struct Cursor<'a> {
-    len_remaining: usize,
-    chars: Chars<'a>,
-    prev: char
+// Walks a stream of characters exactly once, tracking the byte offset of the
+// next character to be consumed. Consumed characters go into a `LineIndex`,
+// which lines and columns (in chars) are taken from.
+#[derive(Debug, Clone)]
+pub(crate) struct Cursor<I: Iterator<Item = char>> {
+    chars: I,
+    // Characters peeked at but not yet consumed.
+    lookahead: VecDeque<char>,
+    index: usize,
+    // Byte offset and position the cursor started at, where `line_index` begins.
+    start: usize,
+    origin: LineCol,
+    line_index: LineIndex,
+    lexeme: String,
 }
 
 pub(crate) const EOF_CHAR: char = '\0';
 
-impl<'a> Cursor<'a> {
-    pub fn new(source: &'a str) -> Self {
+impl<I: Iterator<Item = char>> Cursor<I> {
+    /// A cursor over `chars` taken to start at byte offset `index`, which is at `origin`.
+    pub fn at(chars: I, index: usize, origin: LineCol) -> Self {
         Self {
-            len_remaining: source.len(),
-            chars: source.chars(),
-            prev: EOF_CHAR
+            chars,
+            lookahead: VecDeque::new(),
+            index,
+            start: index,
+            origin,
+            line_index: LineIndex::default(),
+            lexeme: String::new(),
         }
     }
 
-    pub(crate) fn prev(&mut self) -> char {
-        self.prev
+    /// The `n`th character after the next one, without consuming anything.
+    pub(crate) fn nth(&mut self, n: usize) -> Option<char> {
+        while self.lookahead.len() <= n {
+            self.lookahead.push_back(self.chars.next()?);
+        }
+        Some(self.lookahead[n])
     }
 
     pub(crate) fn first(&mut self) -> char {
-        self.chars.clone().next().unwrap_or(EOF_CHAR)
+        self.nth(0).unwrap_or(EOF_CHAR)
+    }
+
+    pub(crate) fn is_eof(&mut self) -> bool {
+        self.nth(0).is_none()
+    }
+
+    /// Whether the upcoming characters are `prefix`.
+    pub(crate) fn starts_with(&mut self, prefix: &str) -> bool {
+        prefix
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.nth(i) == Some(c))
+    }
+
+    /// Byte offset of the next character.
+    pub(crate) fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Line and column of the next character.
+    pub(crate) fn line_col(&self) -> LineCol {
+        self.line_col_at(self.index)
+    }
+
+    /// Line and column of a byte offset between the start of the cursor and the next character.
+    pub(crate) fn line_col_at(&self, offset: usize) -> LineCol {
+        self.line_index
+            .wide_line_col_from(self.origin, WideEncoding::Utf32, offset - self.start)
+    }
+
+    /// Characters consumed since the last call to `start_lexeme`.
+    pub(crate) fn lexeme(&self) -> &str {
+        &self.lexeme
+    }
+
+    pub(crate) fn start_lexeme(&mut self) {
+        self.lexeme.clear();
     }
 
-    pub(crate) fn second(&mut self) -> char {
-        let mut chars = self.chars.clone();
-        chars.next();
-        chars.next().unwrap_or(EOF_CHAR)
+    /// Moves to the next character.
+    pub(crate) fn bump(&mut self) -> Option<char> {
+        let c = self.lookahead.pop_front().or_else(|| self.chars.next())?;
+        self.lexeme.push(c);
+        self.index += c.len_utf8();
+        self.line_index.push(c);
+        Some(c)
     }
 
-    pub(crate) fn is_eof(&self) -> bool {
-        self.chars.as_str().is_empty()
+    /// Eats characters while the predicate returns true or until EOF is reached.
+    pub(crate) fn eat_while(&mut self, mut predicate: impl FnMut(char) -> bool) {
+        while !self.is_eof() && predicate(self.first()) {
+            self.bump();
+        }
     }
-}
\ No newline at end of file
+
+    /// Like `eat_while`, but also returns the eaten characters.
+    pub(crate) fn collect_while(&mut self, mut predicate: impl FnMut(char) -> bool) -> String {
+        let mut result = String::new();
+        while !self.is_eof() && predicate(self.first()) {
+            result.extend(self.bump());
+        }
+        result
+    }
+}
@@ -0,0 +1,198 @@
+use crate::diagnostics::{Diagnostic, Label, Report, Suggestion};
+use crate::lex::lexer::{LexerError, LexerWarning};
+
+const ESCAPES_HELP: &str =
+    "the supported escapes are \\n, \\t, \\\\, \\\", \\', \\0, \\x7F and \\u{10FFFF}";
+
+impl Diagnostic for LexerError {
+    fn code(&self) -> &'static str {
+        match self {
+            LexerError::InvalidStringLiteral(..) => "L0002",
+            LexerError::InvalidCharacterLiteral(..) => "L0003",
+            LexerError::UnknownEscape(..) => "L0004",
+            LexerError::InvalidHexEscape(..) => "L0005",
+            LexerError::InvalidUnicodeEscape(..) => "L0006",
+            LexerError::InvalidIntegerLiteral(..) => "L0007",
+            LexerError::IntegerOverflow(..) => "L0008",
+            LexerError::InvalidComment(..) => "L0010",
+            LexerError::InvalidEOF(..) => "L0011",
+            LexerError::UnknownToken(..) => "L0013",
+            LexerError::Unknown(..) => "L0014",
+        }
+    }
+
+    fn report(&self) -> Report {
+        let report = match self {
+            // The position is that of the first character after the opening quote.
+            LexerError::InvalidStringLiteral(line_no, col_no) => {
+                Report::error("unterminated string literal")
+                    .with_label(Label::primary(
+                        *line_no,
+                        col_no.saturating_sub(1).max(1),
+                        1,
+                        "string literal starts here",
+                    ))
+                    .with_help("add a closing `\"`")
+            }
+            LexerError::InvalidCharacterLiteral(line_no, col_no) => {
+                Report::error("invalid character literal")
+                    .with_label(Label::primary(*line_no, *col_no, 1, "literal starts here"))
+                    .with_note("a character literal holds exactly one character, like 'a' or '\\n'")
+            }
+            LexerError::UnknownEscape(c, line_no, col_no) => {
+                Report::error(format!("unknown escape sequence \\{}", c.escape_debug()))
+                    .with_label(Label::primary(*line_no, *col_no, 2, "unknown escape"))
+                    .with_help(ESCAPES_HELP)
+                    .with_suggestion(Suggestion::new(
+                        *line_no,
+                        *col_no,
+                        1,
+                        "\\\\",
+                        "to write a backslash, escape it",
+                    ))
+            }
+            LexerError::InvalidHexEscape(escape, line_no, col_no) => {
+                Report::error(format!("invalid hex escape {}", escape))
+                    .with_label(Label::primary(
+                        *line_no,
+                        *col_no,
+                        escape.chars().count(),
+                        "invalid escape",
+                    ))
+                    .with_help("hex escapes take two hex digits no greater than 7F, like \\x41")
+            }
+            LexerError::InvalidUnicodeEscape(escape, line_no, col_no) => {
+                Report::error(format!("invalid unicode escape {}", escape))
+                    .with_label(Label::primary(
+                        *line_no,
+                        *col_no,
+                        escape.chars().count(),
+                        "invalid escape",
+                    ))
+                    .with_help(
+                        "unicode escapes look like \\u{1F600}, with 1 to 6 hex digits naming a unicode scalar value",
+                    )
+            }
+            LexerError::InvalidIntegerLiteral(raw, line_no, col_no) => {
+                Report::error(format!("invalid integer literal `{}`", raw))
+                    .with_label(Label::primary(
+                        *line_no,
+                        *col_no,
+                        raw.chars().count(),
+                        "not a number",
+                    ))
+                    .with_help("integer literals are decimal digits, or hex, binary or octal digits after 0x, 0b or 0o, optionally separated by `_`")
+            }
+            LexerError::IntegerOverflow(raw, line_no, col_no) => {
+                Report::error("integer literal is too large")
+                    .with_label(Label::primary(
+                        *line_no,
+                        *col_no,
+                        raw.chars().count(),
+                        "does not fit in a 64-bit signed integer",
+                    ))
+                    .with_note("the largest integer literal is 9223372036854775808, which is only valid negated")
+            }
+            LexerError::InvalidComment(line_no, col_no) => {
+                Report::error("unterminated block comment")
+                    .with_label(Label::primary(*line_no, *col_no, 2, "comment starts here"))
+                    .with_note("block comments nest, so each one opened inside needs closing too")
+            }
+            LexerError::InvalidEOF(line_no, col_no) => Report::error("unexpected end of file")
+                .with_label(Label::primary(*line_no, *col_no, 0, "")),
+            LexerError::UnknownToken(token) => {
+                Report::error(format!("unexpected character {:?}", token.text()))
+                    .with_label(Label::token(token, "not valid here"))
+            }
+            LexerError::Unknown(line_no, col_no) => Report::error("unknown lexer error")
+                .with_label(Label::primary(*line_no, *col_no, 1, "")),
+        };
+        report.with_code(self.code())
+    }
+}
+
+impl Diagnostic for LexerWarning {
+    fn code(&self) -> &'static str {
+        match self {
+            LexerWarning::MixedScriptIdentifier(..) => "W0001",
+        }
+    }
+
+    fn report(&self) -> Report {
+        let report = match self {
+            LexerWarning::MixedScriptIdentifier(name, line_no, col_no, scripts) => {
+                Report::warning(format!("identifier `{}` mixes scripts", name))
+                    .with_label(Label::primary(
+                        *line_no,
+                        *col_no,
+                        name.chars().count(),
+                        format!("mixes the {} scripts", scripts),
+                    ))
+                    .with_note("characters from different scripts can look alike, so this name may be confused with another")
+            }
+        };
+        report.with_code(self.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Renderer;
+    use crate::lex::Lexer;
+    use crate::source::SourceMap;
+
+    fn render_errors(source: &str) -> Vec<String> {
+        let mut map = SourceMap::new();
+        let file = map.add_file("test.t", source);
+        let (_, errors, _) = Lexer::for_file(file).lex_recovering(source);
+        errors
+            .iter()
+            .map(|e| Renderer::new().render(&e.report(), map.get(file).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn renders_lexer_errors() {
+        let errors = render_errors("var $x\nsprint(\"a\\q\")\n");
+        assert_eq!(
+            errors,
+            vec![
+                "error[L0013]: unexpected character \"$\"
+ --> test.t:1:5
+  |
+1 | var $x
+  |     ^ not valid here
+",
+                "error[L0004]: unknown escape sequence \\q
+ --> test.t:2:10
+  |
+2 | sprint(\"a\\q\")
+  |          ^^ unknown escape
+  |
+  = help: the supported escapes are \\n, \\t, \\\\, \\\", \\', \\0, \\x7F and \\u{10FFFF}
+  = help: to write a backslash, escape it: `\\\\`
+",
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_unterminated_literals_at_their_start() {
+        let errors = render_errors("x : \"abc\n");
+        assert!(errors[0].contains("1 | x : \"abc\n  |     ^ string literal starts here\n"));
+    }
+
+    #[test]
+    fn renders_lint_warnings() {
+        let source = "var pаypal\n";
+        let mut tokens = Lexer::new().tokens(source);
+        tokens.by_ref().for_each(drop);
+        let mut map = SourceMap::new();
+        let file = map.add_file("test.t", source);
+        let report = tokens.take_warnings()[0].report();
+        let rendered = Renderer::new().render(&report, map.get(file).unwrap());
+        assert!(rendered.starts_with("warning[W0001]: identifier `pаypal` mixes scripts\n"));
+        assert!(rendered.contains("  |     ^^^^^^ mixes the Latin, Cyrillic scripts\n"));
+    }
+}
@@ -1,49 +1,88 @@
-use crate::lex::token::{Token, TokenType};
-use crate::lex::types::{KeywordToken, LiteralToken};
-use anyhow::{bail, ensure, Result};
+use crate::lex::config::LexerConfig;
+use crate::lex::cursor::Cursor;
+use crate::lex::reader::SourceReader;
+use crate::lex::token::{Token, TokenKind, TokenType, Trivia};
+use crate::lex::types::LiteralToken;
+use crate::source::{FileId, LineCol, SourceMap, Span};
+use crate::symbol::Symbol;
+use anyhow::{bail, ensure, Context, Result};
 use log::debug;
+use serde::{Deserialize, Serialize};
 use std::fmt::Display;
+use std::io::BufRead;
+use std::path::Path;
+use std::str::Chars;
+use std::sync::Arc;
 use thiserror::Error;
+use unicode_normalization::UnicodeNormalization;
+use unicode_script::{Script, ScriptExtension, UnicodeScript};
+use unicode_xid::UnicodeXID;
 
 #[derive(Debug, Clone)]
 pub struct Lexer {
-    source: String,
-    index: usize,
-    curr_char: char,
-    line_no: usize,
-    col_no: usize,
-    length: usize,
+    // The file the spans of lexed tokens point into.
+    file: FileId,
+    // Whether whitespace and comments are kept as trivia on the tokens.
+    lossless: bool,
+    config: Arc<LexerConfig>,
 }
 
 #[derive(Debug, Error)]
 pub enum LexerError {
-    #[error("Unexpected character: {0:?} at {1}:{2}")]
-    InvalidCharacter(char, usize, usize),
     #[error("Invalid string literal at {0}:{1}")]
     InvalidStringLiteral(usize, usize),
     #[error("Invalid character literal at {0}:{1}")]
     InvalidCharacterLiteral(usize, usize),
-    #[error("Invalid integer literal")]
-    InvalidIntegerLiteral(#[from] std::num::ParseIntError),
-    #[error("Invalid identifier at {0}:{1}")]
-    InvalidIdentifier(usize, usize),
+    #[error("Unknown escape sequence \\{0} at {1}:{2}")]
+    UnknownEscape(char, usize, usize),
+    #[error("Invalid hex escape {0} at {1}:{2}: expected two hex digits no greater than \\x7F")]
+    InvalidHexEscape(String, usize, usize),
+    #[error("Invalid unicode escape {0} at {1}:{2}: expected \\u{{...}} with 1 to 6 hex digits naming a unicode scalar value")]
+    InvalidUnicodeEscape(String, usize, usize),
+    #[error("Invalid integer literal {0} at {1}:{2}")]
+    InvalidIntegerLiteral(String, usize, usize),
+    #[error("Integer literal {0} at {1}:{2} does not fit in a 64-bit signed integer")]
+    IntegerOverflow(String, usize, usize),
     #[error("Invalid comment at {0}:{1}")]
     InvalidComment(usize, usize),
     #[error("Unexpected EOF at {0}:{1}")]
     InvalidEOF(usize, usize),
-    #[error("Unexpected EOL at {0}:{1}")]
-    InvalidEOL(usize, usize),
-    #[error("Invalid token {0:?} at {2}:{3}[1]")]
-    UnknownToken(Token, usize, usize, usize),
+    #[error("Invalid token {:?} at {}:{}", .0.text(), .0.line_no(), .0.col_no())]
+    UnknownToken(Token),
     #[error("Unknown lexer error at {0}:{1}")]
     Unknown(usize, usize),
 }
 
-#[derive(Debug)]
+/// Problems that do not stop lexing but are worth pointing out.
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+pub enum LexerWarning {
+    #[error(
+        "Identifier {0} at {1}:{2} mixes the {3} scripts and may be confused with another name"
+    )]
+    MixedScriptIdentifier(String, usize, usize, String),
+}
+
+/// Version of the JSON produced by `TokenStream::to_json`. Bump it whenever
+/// the serialized shape of `Token` or anything inside it changes.
+pub const TOKEN_JSON_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct TokenStream {
     pub tokens: Vec<Token>,
 }
 
+#[derive(Serialize)]
+struct TokenJson<'a> {
+    version: u32,
+    tokens: &'a [Token],
+}
+
+#[derive(Deserialize)]
+struct TokenJsonOwned {
+    version: u32,
+    tokens: Vec<Token>,
+}
+
 impl Display for TokenStream {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for token in &self.tokens {
@@ -94,6 +133,61 @@ impl TokenStream {
             .collect::<TokenStream>()
     }
 
+    /// Serializes the stream for external tools. The document looks like
+    ///
+    /// ```json
+    /// {
+    ///   "version": 1,
+    ///   "tokens": [
+    ///     {
+    ///       "token_type": { "IdentifierToken": "i" },
+    ///       "span": { "file": 0, "start": 4, "end": 5 },
+    ///       "line_no": 1,
+    ///       "col_no": 5,
+    ///       "trailing_trivia": [{ "Whitespace": " " }]
+    ///     },
+    ///     { "token_type": { "Syntax": "Assign" }, ... },
+    ///     { "token_type": { "Literal": { "Integer": { "value": 1, "raw": "1" } } }, ... },
+    ///     { "token_type": { "Literal": { "String": { "value": "a\n", "raw": "\"a\\n\"" } } }, ... },
+    ///     { "token_type": { "Keyword": "Var" }, ... },
+    ///     { "token_type": "NL", ... },
+    ///     { "token_type": "Eof", ... }
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// Enum variants are named as in `TokenType`, `KeywordToken`, `SyntaxToken`,
+    /// `LiteralToken` and `Trivia`. Spans are byte offsets, lines and columns
+    /// start at 1. `leading_trivia` and `trailing_trivia` are left out when
+    /// empty. `version` is `TOKEN_JSON_VERSION`.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&TokenJson {
+            version: TOKEN_JSON_VERSION,
+            tokens: &self.tokens,
+        })
+        .expect("tokens always serialize")
+    }
+
+    /// Reads a stream written by `to_json`, refusing documents of any other version.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let document: TokenJsonOwned = serde_json::from_str(json).context("Invalid token JSON")?;
+        ensure!(
+            document.version == TOKEN_JSON_VERSION,
+            "Unsupported token JSON version {}, expected {}",
+            document.version,
+            TOKEN_JSON_VERSION
+        );
+        Ok(TokenStream {
+            tokens: document.tokens,
+        })
+    }
+
+    /// Concatenates the text and trivia of every token. For a stream lexed in
+    /// lossless mode this is exactly the source it was lexed from.
+    pub fn to_source(&self) -> String {
+        self.tokens.iter().map(Token::full_text).collect()
+    }
+
     pub fn reverse(&self) -> Self {
         let mut tokens = self.tokens.clone();
         tokens.reverse();
@@ -110,100 +204,325 @@ impl FromIterator<Token> for TokenStream {
 
 impl Lexer {
     pub fn new() -> Self {
+        Lexer::with_config(LexerConfig::default())
+    }
+
+    /// A lexer for the dialect described by `config`.
+    pub fn with_config(config: LexerConfig) -> Self {
         Lexer {
-            source: String::new(),
-            index: 0,
-            curr_char: '\0',
-            line_no: 1,
-            col_no: 1,
-            length: 0,
+            file: FileId::default(),
+            lossless: false,
+            config: Arc::new(config),
         }
     }
 
+    pub fn config(&self) -> &LexerConfig {
+        &self.config
+    }
+
+    /// A lexer whose tokens have spans pointing into `file`.
+    pub fn for_file(file: FileId) -> Self {
+        Lexer {
+            file,
+            ..Lexer::new()
+        }
+    }
+
+    /// In lossless mode every token carries the whitespace and comments around
+    /// it as trivia, so that concatenating the `full_text` of all tokens gives
+    /// back the source exactly. Trivia up to the end of a line trails the token
+    /// before it, anything after a newline leads the token after it.
+    pub fn lossless(mut self, lossless: bool) -> Self {
+        self.lossless = lossless;
+        self
+    }
+
+    /// Lexes a file previously loaded into `sources`.
+    pub fn lex_source_file(&mut self, sources: &SourceMap, file: FileId) -> Result<TokenStream> {
+        let source = sources
+            .get(file)
+            .with_context(|| format!("No file {:?} in the source map", file))?;
+        self.file = file;
+        self.lex_str(source.source())
+    }
+
     pub fn lex(&mut self, source: String) -> Result<TokenStream> {
-        self.source = source;
-        self.length = self.source.len();
-        self.curr_char = self.get_curr()?;
-        let mut tokens = Vec::new();
-        while self.has_next() {
-            tokens.push(self.get_next_token()?);
+        self.lex_str(&source)
+    }
+
+    /// Lexes `source` as given. Unlike `lex_reader`, a byte order mark or CRLF
+    /// line endings are kept, so spans refer to `source` itself; use
+    /// `lex_source_file` for spans into the normalised text of a `SourceMap`.
+    pub fn lex_str(&mut self, source: &str) -> Result<TokenStream> {
+        self.lex_chars(source.chars())
+    }
+
+    /// Lexes a file without reading it into memory first. Spans refer to the
+    /// normalised text `SourceMap::load_file` stores for the same file.
+    pub fn lex_file<P: AsRef<Path>>(&mut self, path: P) -> Result<TokenStream> {
+        let path = path.as_ref();
+        let reader = SourceReader::from_file(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        self.lex_buffered(reader)
+    }
+
+    /// Lexes anything buffered, e.g. `io::stdin().lock()` or a `BufReader<TcpStream>`.
+    /// Spans refer to the text after `normalize`, see `SourceReader`.
+    pub fn lex_reader<R: BufRead>(&mut self, reader: R) -> Result<TokenStream> {
+        self.lex_buffered(SourceReader::new(reader))
+    }
+
+    fn lex_buffered<R: BufRead>(&mut self, mut source: SourceReader<R>) -> Result<TokenStream> {
+        let tokens = self.lex_chars(source.by_ref());
+        // A read error cuts the stream short, which the lexer would otherwise
+        // report as a confusing syntax error at the point where it stopped.
+        if let Some(e) = source.take_error() {
+            return Err(e).context("Failed to read source");
         }
-        if !self.has_next() {
-            debug!("No more tokens to lex");
-            tokens.push(TokenType::Eof.at(self.index, self.line_no, self.col_no));
+        tokens
+    }
+
+    /// Lexes `source` without stopping at the first error.
+    ///
+    /// Every span the lexer can't make sense of becomes a `TokenType::Error`
+    /// token and its `LexerError` is recorded, then lexing picks up right after
+    /// it. The stream always ends with an `Eof` token. Lint warnings are
+    /// returned along with the errors.
+    pub fn lex_recovering(
+        &mut self,
+        source: &str,
+    ) -> (TokenStream, Vec<LexerError>, Vec<LexerWarning>) {
+        let mut tokens = self.tokens(source).recovering();
+        let stream = tokens.by_ref().flatten().collect::<TokenStream>();
+        let errors = tokens.take_errors();
+        let warnings = tokens.take_warnings();
+        info!(
+            "Lexed {} tokens with {} errors and {} warnings",
+            stream.tokens.len(),
+            errors.len(),
+            warnings.len()
+        );
+        (stream, errors, warnings)
+    }
+
+    /// Lexes `source` lazily, one token per call to `next`.
+    pub fn tokens<'a>(&self, source: &'a str) -> Tokens<Chars<'a>> {
+        self.tokens_from(source.chars())
+    }
+
+    /// Lexes any stream of characters lazily, e.g. a `SourceReader`.
+    pub fn tokens_from<I: IntoIterator<Item = char>>(&self, chars: I) -> Tokens<I::IntoIter> {
+        self.tokens_at(chars, self.lossless, 0, LineCol::new(1, 1))
+    }
+
+    // Lexes `chars` as if they started at byte offset `index` of the file, which is at `origin`.
+    pub(crate) fn tokens_at<I: IntoIterator<Item = char>>(
+        &self,
+        chars: I,
+        lossless: bool,
+        index: usize,
+        origin: LineCol,
+    ) -> Tokens<I::IntoIter> {
+        Tokens {
+            scanner: Scanner::new(
+                self.file,
+                lossless,
+                self.config.clone(),
+                Cursor::at(chars.into_iter(), index, origin),
+            ),
+            pending: None,
+            error: None,
+            errors: None,
+            finished: false,
         }
+    }
 
-        info!("Lexed {} tokens", tokens.len());
+    fn lex_chars<I: Iterator<Item = char>>(&mut self, chars: I) -> Result<TokenStream> {
+        let stream = self
+            .tokens_from(chars)
+            .collect::<Result<TokenStream, _>>()?;
+        info!("Lexed {} tokens", stream.tokens.len());
+        Ok(stream)
+    }
+}
 
-        // Post processing.
-        // fold consecutive NL tokens into one.
-        // find a NL token, then delete all the NL tokens until the next non-NL token.
-        let indices = tokens
-            .iter()
-            .enumerate()
-            .filter(|(_, t)| t.token_type() == TokenType::NL)
-            .map(|(i, _)| i)
-            .collect::<Vec<usize>>();
-        let indices_folded = indices
-            .iter()
-            .fold((Vec::new(), Vec::new()), |mut acc, i| {
-                if let Some(last) = acc.0.last() {
-                    if i - last == 1 {
-                        acc.1.push(*last);
-                        acc.0.pop();
-                    }
+/// Lazily lexes a stream of characters.
+///
+/// Consecutive NL tokens are folded into one, keeping the last of each run, and
+/// in lossless mode trivia is attached as described on `Lexer::lossless`. Both
+/// need to see the token after the current one, so the iterator scans one
+/// token ahead. The last item is the `Eof` token, unless an error ends
+/// iteration first.
+pub struct Tokens<I: Iterator<Item = char>> {
+    scanner: Scanner<I>,
+    // The last token scanned, held back until we know what follows it.
+    pending: Option<Token>,
+    // An error to yield once `pending` has been yielded.
+    error: Option<LexerError>,
+    // Errors recovered from so far, if recovering.
+    errors: Option<Vec<LexerError>>,
+    finished: bool,
+}
+
+impl<I: Iterator<Item = char>> Tokens<I> {
+    /// Instead of ending at the first error, turn bad spans into `Error` tokens
+    /// and keep going. The errors can be retrieved with `take_errors`.
+    pub fn recovering(mut self) -> Self {
+        self.errors.get_or_insert_with(Vec::new);
+        self
+    }
+
+    /// The errors recovered from so far.
+    pub fn take_errors(&mut self) -> Vec<LexerError> {
+        self.errors.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// The warnings raised so far. Unlike errors these never affect the tokens.
+    pub fn take_warnings(&mut self) -> Vec<LexerWarning> {
+        std::mem::take(&mut self.scanner.warnings)
+    }
+
+    fn scan(&mut self) -> Option<Token> {
+        match self.scanner.get_next_token() {
+            Ok(token) => Some(token),
+            Err(e) => match &mut self.errors {
+                Some(errors) => {
+                    let (token, error) = self.scanner.recover(e);
+                    debug!("Recovered from {} with {:?}", error, token);
+                    errors.push(error);
+                    Some(token)
                 }
-                acc.0.push(*i);
-                acc
-            })
-            .1;
+                None => {
+                    self.error = Some(self.scanner.lexer_error(e));
+                    None
+                }
+            },
+        }
+    }
+}
+
+impl<I: Iterator<Item = char>> Iterator for Tokens<I> {
+    type Item = Result<Token, LexerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            let Some(mut token) = self.scan() else {
+                self.finished = true;
+                break;
+            };
+            self.finished = token.kind() == TokenKind::Eof;
+            let trivia = self.scanner.take_trivia();
+            let Some(mut prev) = self.pending.take() else {
+                token.leading_trivia_mut().extend(trivia);
+                self.pending = Some(token);
+                continue;
+            };
+            // Trivia up to the end of a line trails the token before it.
+            if prev.kind() != TokenKind::NL {
+                prev.trailing_trivia_mut().extend(trivia);
+                self.pending = Some(token);
+                return Some(Ok(prev));
+            }
+            token.leading_trivia_mut().extend(trivia);
+            if token.kind() == TokenKind::NL {
+                // Fold `prev` into `token`, keeping its text as trivia.
+                let mut leading = std::mem::take(prev.leading_trivia_mut());
+                leading.push(Trivia::Newline);
+                leading.append(token.leading_trivia_mut());
+                *token.leading_trivia_mut() = leading;
+                self.pending = Some(token);
+                continue;
+            }
+            self.pending = Some(token);
+            return Some(Ok(prev));
+        }
+        match self.pending.take() {
+            Some(token) => Some(Ok(token)),
+            None => self.error.take().map(Err),
+        }
+    }
+}
+
+/// Single pass scanner over a stream of characters. Every character is visited
+/// once through the `Cursor`, so lexing is linear in the size of the input.
+struct Scanner<I: Iterator<Item = char>> {
+    cursor: Cursor<I>,
+    file: FileId,
+    config: Arc<LexerConfig>,
+    // Byte offset and position of the token being lexed.
+    token_start: (usize, LineCol),
+    // Trivia lexed since the last token, only collected in lossless mode.
+    trivia: Option<Vec<Trivia>>,
+    warnings: Vec<LexerWarning>,
+}
 
-        for i in indices_folded.iter().rev() {
-            tokens.remove(*i);
+impl<I: Iterator<Item = char>> Scanner<I> {
+    fn new(file: FileId, lossless: bool, config: Arc<LexerConfig>, cursor: Cursor<I>) -> Self {
+        Scanner {
+            token_start: (cursor.index(), cursor.line_col()),
+            cursor,
+            file,
+            config,
+            trivia: lossless.then(Vec::new),
+            warnings: Vec::new(),
         }
+    }
 
-        info!("Folded {} NL tokens", indices_folded.len());
-        info!("Now {} tokens", tokens.len());
+    fn push_trivia(&mut self, trivia: impl FnOnce(String) -> Trivia) {
+        if let Some(pending) = &mut self.trivia {
+            pending.push(trivia(self.cursor.lexeme().to_string()));
+        }
+    }
 
-        Ok(TokenStream::from_iter(tokens))
+    fn take_trivia(&mut self) -> Vec<Trivia> {
+        self.trivia.as_mut().map(std::mem::take).unwrap_or_default()
     }
 
-    fn has_next(&self) -> bool {
-        self.index < self.length
+    /// Turns the error of a failed `get_next_token` into an `Error` token
+    /// covering everything consumed for the token so far, skipping ahead where
+    /// needed so lexing can resume at a sensible place.
+    fn recover(&mut self, e: anyhow::Error) -> (Token, LexerError) {
+        let error = self.lexer_error(e);
+        if let LexerError::InvalidCharacterLiteral(..) = error {
+            self.skip_character_literal();
+        }
+        if self.cursor.lexeme().is_empty() {
+            self.cursor.bump();
+        }
+        let token = self.finish_token(TokenType::Error(self.cursor.lexeme().to_string()));
+        (token, error)
     }
 
-    fn reset(&mut self) {
-        self.index = 0;
-        self.curr_char = self.source.chars().next().unwrap();
-        self.line_no = 1;
-        self.col_no = 1;
-        self.length = self.source.chars().count();
+    fn lexer_error(&self, e: anyhow::Error) -> LexerError {
+        let (line_no, col_no) = self.token_start_position();
+        e.downcast::<LexerError>().unwrap_or_else(|e| {
+            warn!("Unexpected error while lexing: {}", e);
+            LexerError::Unknown(line_no, col_no)
+        })
     }
 
-    fn get_curr(&self) -> Result<char> {
-        ensure!(
-            self.has_next(),
-            LexerError::InvalidEOF(self.line_no, self.col_no)
-        );
-        let c = self.source.chars().nth(self.index).unwrap();
-        trace!(
-            "Got current character: {:?} at {}:{}[{}]",
-            c,
-            self.line_no,
-            self.col_no,
-            self.index
-        );
-        Ok(c)
+    fn has_next(&mut self) -> bool {
+        !self.cursor.is_eof()
+    }
+
+    fn curr_char(&mut self) -> char {
+        self.cursor.first()
+    }
+
+    fn token_here(&mut self, token_type: TokenType) -> Token {
+        let index = self.cursor.index();
+        let LineCol { line, col } = self.cursor.line_col();
+        token_type.at(Span::new(self.file, index, index), line, col)
+    }
+
+    // Builds a token spanning from the start of the current token to the cursor.
+    fn finish_token(&self, token_type: TokenType) -> Token {
+        let (index, LineCol { line, col }) = self.token_start;
+        token_type.at(Span::new(self.file, index, self.cursor.index()), line, col)
     }
 
     fn advance(&mut self) -> Result<()> {
-        self.index += 1;
-        self.col_no += 1;
-        if self.has_next() {
-            self.curr_char = self.get_curr()?;
-            // trace!("Advanced to {}:{}[{}]", self.line_no, self.col_no, self.index);
-        }
-        Ok(())
+        self.consume().map(|_| ())
     }
 
     fn advance_n(&mut self, n: usize) -> Result<()> {
@@ -213,223 +532,408 @@ impl Lexer {
         Ok(())
     }
 
-    fn advance_until(&mut self, pred: Box<dyn Fn() -> bool>) -> Result<()> {
-        while self.has_next() && !pred() {
-            self.advance()?;
-        }
-        Ok(())
+    // Advances to the end of the line, or of the file if there is no newline.
+    fn advance_eol(&mut self) {
+        self.cursor.eat_while(|c| c != '\n');
     }
 
-    fn advance_eol(&mut self) -> Result<()> {
-        if let Some(n) = self.find_next('\n') {
-            self.advance_n(n)?;
-        } else {
-            bail!(LexerError::InvalidEOL(self.line_no, self.col_no));
+    // Advances past a block comment starting at the cursor, including any
+    // comments nested inside it.
+    fn skip_block_comment(&mut self, open: &str, close: &str) -> Result<()> {
+        let (line_no, col_no) = self.token_start_position();
+        let mut depth = 0;
+        loop {
+            if self.cursor.starts_with(open) {
+                self.advance_n(open.chars().count())?;
+                depth += 1;
+            } else if self.cursor.starts_with(close) {
+                self.advance_n(close.chars().count())?;
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(());
+                }
+            } else {
+                ensure!(
+                    self.cursor.bump().is_some(),
+                    LexerError::InvalidComment(line_no, col_no)
+                );
+            }
         }
-        Ok(())
     }
 
-    fn consume(&mut self) -> Result<char> {
-        let c = self.curr_char;
-        self.advance()?;
-        Ok(c)
+    // Collects the doc comment starting at the cursor, which begins with `intro`.
+    fn collect_doc_comment(&mut self, intro: &str) -> TokenType {
+        self.advance_eol();
+        let raw = self.cursor.lexeme().to_string();
+        let text = &raw[intro.len()..];
+        TokenType::DocComment {
+            text: text.strip_prefix(' ').unwrap_or(text).to_string(),
+            raw,
+        }
     }
 
-    fn try_consume(&mut self) -> Result<Option<char>> {
-        if self.has_next() {
-            let c = self.curr_char;
-            self.advance()?;
-            Ok(Some(c))
-        } else {
-            Ok(None)
+    fn consume(&mut self) -> Result<char> {
+        let (line_no, col_no) = self.position(self.cursor.index());
+        match self.cursor.bump() {
+            Some(c) => Ok(c),
+            None => bail!(LexerError::InvalidEOF(line_no, col_no)),
         }
     }
 
-    fn peek(&self) -> Result<Option<char>> {
-        if !self.has_next() {
-            return Ok(None);
-        }
-        let c = self.source.chars().nth(self.index + 1).unwrap();
-        trace!(
-            "Peeked character: {:?} at {}:{}[{}]",
-            c,
-            self.line_no,
-            self.col_no,
-            self.index
-        );
-        Ok(Some(c))
+    // Advance the index until the current character is not a whitespace character. This excludes \n
+    // unless newlines are insignificant.
+    fn skip_whitespace(&mut self) {
+        let newlines = !self.config.significant_newlines;
+        self.cursor
+            .eat_while(|c| is_whitespace(c) || (newlines && c == '\n'));
     }
 
-    fn collect(&mut self, n: usize) -> Result<&str> {
-        let start = self.index;
-        let end = self.index + n;
-        self.advance_n(n)?;
-        Ok(&self.source[start..end])
+    fn is_whitespace(&self, c: char) -> bool {
+        is_whitespace(c) || (c == '\n' && !self.config.significant_newlines)
     }
 
-    fn find_next(&mut self, c: char) -> Option<usize> {
-        let mut i = self.index;
-        while i < self.length {
-            if self.source.chars().nth(i).unwrap() == c {
-                return Some(i);
-            }
-            i += 1;
-        }
-        None
+    // Identifiers follow UAX #31: an XID_Start character or `_`, then any
+    // number of XID_Continue characters.
+    fn is_identifier_start(&self, c: char) -> bool {
+        c.is_xid_start() || (c == '_' && self.config.underscore_in_identifiers)
     }
 
-    // Advance the index until the current character is not a whitespace character. This excludes \n
-    fn skip_whitespace(&mut self) -> Result<()> {
-        while self.is_whitespace() && self.has_next() {
-            self.advance()?;
+    // Skip the rest of a character literal so its closing quote doesn't open a new one.
+    fn skip_character_literal(&mut self) {
+        self.cursor.eat_while(|c| c != '\'' && c != '\n');
+        if self.curr_char() == '\'' {
+            self.cursor.bump();
         }
-        Ok(())
     }
 
+    // Collects the decoded contents of a string literal. The opening quote has
+    // already been consumed; the closing one is consumed here. A bad escape does
+    // not end the literal: it is reported once the whole string has been read.
     fn collect_string(&mut self) -> Result<String> {
-        let mut result = String::new();
-        while self.curr_char != '"' {
-            result.push(self.consume()?);
+        let (line_no, col_no) = self.position(self.cursor.index());
+        let mut string = String::new();
+        let mut escape_error = None;
+        loop {
+            ensure!(
+                self.has_next(),
+                LexerError::InvalidStringLiteral(line_no, col_no)
+            );
+            match self.consume()? {
+                '"' => break,
+                '\\' => match self.collect_escape() {
+                    Ok(c) => string.push(c),
+                    Err(e) => {
+                        escape_error.get_or_insert(e);
+                    }
+                },
+                c => string.push(c),
+            }
+        }
+        match escape_error {
+            Some(e) => Err(e),
+            None => Ok(string),
         }
+    }
+
+    // Decodes an escape sequence whose backslash has just been consumed.
+    fn collect_escape(&mut self) -> Result<char> {
+        let (line_no, col_no) = self.position(self.cursor.index() - 1);
+        ensure!(self.has_next(), {
+            let (line_no, col_no) = self.position(self.cursor.index());
+            LexerError::InvalidEOF(line_no, col_no)
+        });
+        let c = match self.curr_char() {
+            'n' => '\n',
+            't' => '\t',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            '0' => '\0',
+            'x' => {
+                self.advance()?;
+                let digits = self.collect_hex_digits(2);
+                return u8::from_str_radix(&digits, 16)
+                    .ok()
+                    .filter(|b| digits.len() == 2 && b.is_ascii())
+                    .map(char::from)
+                    .ok_or_else(|| {
+                        LexerError::InvalidHexEscape(format!("\\x{}", digits), line_no, col_no)
+                            .into()
+                    });
+            }
+            'u' => {
+                self.advance()?;
+                let mut escape = String::from("\\u");
+                let mut value = None;
+                if self.curr_char() == '{' {
+                    escape.push(self.consume()?);
+                    let digits = self.collect_hex_digits(6);
+                    escape.push_str(&digits);
+                    if self.curr_char() == '}' {
+                        escape.push(self.consume()?);
+                        value = u32::from_str_radix(&digits, 16)
+                            .ok()
+                            .and_then(char::from_u32);
+                    }
+                }
+                return value.ok_or_else(|| {
+                    LexerError::InvalidUnicodeEscape(escape, line_no, col_no).into()
+                });
+            }
+            // A newline ends the line even when escaped, so leave it to be
+            // collected as part of the literal.
+            '\n' => bail!(LexerError::UnknownEscape('\n', line_no, col_no)),
+            c => {
+                self.advance()?;
+                bail!(LexerError::UnknownEscape(c, line_no, col_no))
+            }
+        };
         self.advance()?;
-        Ok(result)
+        Ok(c)
     }
 
-    fn collect_identifier(&mut self) -> Result<String> {
-        let mut result = String::new();
-        while self.curr_char.is_alphanumeric() {
-            result.push(self.consume()?);
+    fn collect_hex_digits(&mut self, max: usize) -> String {
+        let mut count = 0;
+        self.cursor.collect_while(|c| {
+            count += 1;
+            count <= max && c.is_ascii_hexdigit()
+        })
+    }
+
+    // Collects an identifier and interns its NFC form, so that names which
+    // only differ in how their accents are encoded are the same symbol. NFC
+    // text, which is all ASCII text, is read straight out of the lexeme buffer,
+    // so lexing a name that has been interned before does not allocate.
+    fn collect_identifier(&mut self) -> Symbol {
+        let underscore = self.config.underscore_in_identifiers;
+        self.cursor
+            .eat_while(|c| c.is_xid_continue() && (underscore || c != '_'));
+        let identifier = self.cursor.lexeme();
+        if identifier.is_ascii() {
+            return Symbol::intern(identifier);
         }
-        Ok(result)
+        let symbol = if unicode_normalization::is_nfc(identifier) {
+            Symbol::intern(identifier)
+        } else {
+            Symbol::intern(&identifier.nfc().collect::<String>())
+        };
+        self.lint_mixed_scripts(symbol.as_str());
+        symbol
     }
 
-    fn collect_integer(&mut self) -> Result<i64> {
-        let mut result = String::new();
-        while self.curr_char.is_numeric() && self.has_next() {
-            result.push(self.consume()?);
+    // Warns about identifiers with no script in common between all their
+    // characters, such as a Latin name with a Cyrillic `а` in it.
+    fn lint_mixed_scripts(&mut self, identifier: &str) {
+        if !ScriptExtension::for_str(identifier).is_empty() {
+            return;
+        }
+        let mut scripts = Vec::new();
+        for script in identifier.chars().map(|c| c.script()) {
+            if !matches!(script, Script::Common | Script::Inherited) && !scripts.contains(&script) {
+                scripts.push(script);
+            }
+        }
+        let scripts = scripts
+            .iter()
+            .map(|s| s.full_name())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let (line_no, col_no) = self.token_start_position();
+        let warning =
+            LexerWarning::MixedScriptIdentifier(identifier.to_string(), line_no, col_no, scripts);
+        warn!("{}", warning);
+        self.warnings.push(warning);
+    }
+
+    // Collects a decimal, `0x` hex, `0b` binary or `0o` octal literal, with
+    // optional `_` separators. Trailing letters are taken as part of the literal
+    // so that `12ab` is reported as one bad literal rather than two tokens.
+    fn collect_integer(&mut self) -> Result<(u64, String)> {
+        let (line_no, col_no) = self.token_start_position();
+        let raw = self
+            .cursor
+            .collect_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        let (radix, digits) = match raw.get(..2) {
+            Some("0x") => (16, &raw[2..]),
+            Some("0b") => (2, &raw[2..]),
+            Some("0o") => (8, &raw[2..]),
+            _ => (10, raw.as_str()),
+        };
+        let digits = digits.chars().filter(|&c| c != '_');
+        ensure!(
+            digits.clone().next().is_some() && digits.clone().all(|c| c.is_digit(radix)),
+            LexerError::InvalidIntegerLiteral(raw, line_no, col_no)
+        );
+        // Literals go up to i64::MAX + 1 so that the parser can fold `-9223372036854775808`.
+        let value = digits
+            .map(|c| c.to_digit(radix).unwrap() as u64)
+            .try_fold(0u64, |acc, d| acc.checked_mul(radix as u64)?.checked_add(d))
+            .filter(|&v| v <= i64::MIN.unsigned_abs());
+        match value {
+            Some(value) => Ok((value, raw)),
+            None => bail!(LexerError::IntegerOverflow(raw, line_no, col_no)),
         }
-        let parsed = result.parse::<i64>()?;
-        Ok(parsed)
     }
 
-    fn is_whitespace(&self) -> bool {
-        matches!(self.curr_char, ' ' | '\t' | '\r' | '\x0B' | '\x0C')
+    fn token_start_position(&self) -> (usize, usize) {
+        let (_, LineCol { line, col }) = self.token_start;
+        (line, col)
+    }
+
+    // (line_no, col_no) of a byte offset the cursor has passed or is at.
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let LineCol { line, col } = self.cursor.line_col_at(offset);
+        (line, col)
     }
 
     fn get_next_token(&mut self) -> Result<Token> {
-        trace!(
-            "Getting token at {}:{}[{}]",
-            self.line_no,
-            self.col_no,
-            self.index
-        );
-        let start = (self.index, self.line_no, self.col_no);
-        let token = match self.curr_char {
-            n if self.is_whitespace() => {
-                trace!(
-                    "Found whitespace {:?} at {}:{}[{}]",
-                    n,
-                    self.line_no,
-                    self.col_no,
-                    self.index
-                );
-                self.skip_whitespace()?;
-                debug!(
-                    "Skipped {} whitespace from {}:{}[{}] to {}:{}[{}]",
-                    self.index - start.0,
-                    start.1,
-                    start.2,
-                    start.0,
-                    self.line_no,
-                    self.col_no,
-                    self.index
-                );
-                return self.get_next_token();
+        // Trivia is skipped in a loop rather than by recursing, so that a file
+        // full of comments can't overflow the stack.
+        let start = loop {
+            if !self.has_next() {
+                debug!("No more tokens to lex");
+                return Ok(self.token_here(TokenType::Eof));
+            }
+            let start = (self.cursor.index(), self.cursor.line_col());
+            trace!("Getting token at {}[{}]", start.1, start.0);
+            self.token_start = start;
+            self.cursor.start_lexeme();
+
+            // Doc comments are checked first as they usually start with a comment introducer.
+            let config = self.config.clone();
+            let cursor = &mut self.cursor;
+            if let Some(intro) = config.doc_comments.iter().find(|i| cursor.starts_with(i)) {
+                debug!("Found doc comment at {}[{}]", start.1, start.0);
+                let token_type = self.collect_doc_comment(intro);
+                return Ok(self.finish_token(token_type));
             }
+            if let Some((open, close)) = config
+                .block_comments
+                .iter()
+                .find(|(open, _)| cursor.starts_with(open))
+            {
+                debug!("Found block comment at {}[{}]", start.1, start.0);
+                self.skip_block_comment(open, close)?;
+                self.push_trivia(Trivia::Comment);
+                continue;
+            }
+            if config.line_comments.iter().any(|i| cursor.starts_with(i)) {
+                debug!("Found comment at {}[{}]", start.1, start.0);
+                self.advance_eol();
+                self.push_trivia(Trivia::Comment);
+                continue;
+            }
+            let n = self.curr_char();
+            if !self.is_whitespace(n) {
+                break start;
+            }
+            trace!("Found whitespace {:?} at {}[{}]", n, start.1, start.0);
+            self.skip_whitespace();
+            self.push_trivia(Trivia::Whitespace);
+            debug!(
+                "Skipped {} whitespace from {}[{}] to {}[{}]",
+                self.cursor.index() - start.0,
+                start.1,
+                start.0,
+                self.cursor.line_col(),
+                self.cursor.index()
+            );
+        };
+
+        let token_type = match self.curr_char() {
             '\n' => {
-                trace!(
-                    "Found newline at {}:{}[{}]",
-                    self.line_no,
-                    self.col_no,
-                    self.index
-                );
-                // fold newlines into a single token
-                self.col_no = 0;
-                self.line_no += 1;
+                trace!("Found newline at {}[{}]", start.1, start.0);
                 self.advance()?;
                 TokenType::NL
             }
-            // is alphabetic ensures the the identifies starts with a letter. This may not be the behaviour I want.
-            // TODO: Look into this.
-            n if n.is_alphabetic() => {
-                let identifier = self.collect_identifier()?;
-                if let Some(keyword) = KeywordToken::from_str(&identifier) {
-                    debug!(
-                        "Found keyword {:?} at {}:{}[{}]",
-                        keyword, self.line_no, self.col_no, self.index
-                    );
+            n if self.is_identifier_start(n) => {
+                let identifier = self.collect_identifier();
+                if let Some(keyword) = self.config.keyword(identifier.as_str()) {
+                    debug!("Found keyword {:?} at {}[{}]", keyword, start.1, start.0);
                     TokenType::Keyword(keyword)
                 } else {
                     debug!(
-                        "Found identifier {:?} at {}:{}[{}]",
-                        identifier, self.line_no, self.col_no, self.index
+                        "Found identifier {:?} at {}[{}]",
+                        identifier, start.1, start.0
                     );
                     TokenType::IdentifierToken(identifier)
                 }
             }
-            n if n.is_numeric() => {
-                let integer = self.collect_integer()?;
+            n if n.is_ascii_digit() => {
+                let (value, raw) = self.collect_integer()?;
                 debug!(
-                    "Collected integer: {} at {}:{}[{}] to {}:{}[{}]",
-                    integer, start.1, start.2, start.0, self.line_no, self.col_no, self.index
+                    "Collected integer: {} at {}[{}] to {}[{}]",
+                    raw,
+                    start.1,
+                    start.0,
+                    self.cursor.line_col(),
+                    self.cursor.index()
                 );
-                TokenType::Literal(LiteralToken::Integer(integer))
+                TokenType::Literal(LiteralToken::Integer { value, raw })
             }
             '"' => {
                 self.advance()?;
                 let string = self.collect_string()?;
                 debug!(
-                    "Collected string: \"{}\" at {}:{}[{}] to {}:{}[{}]",
-                    string, start.1, start.2, start.0, self.line_no, self.col_no, self.index
+                    "Collected string: {} at {}[{}] to {}[{}]",
+                    self.cursor.lexeme(),
+                    start.1,
+                    start.0,
+                    self.cursor.line_col(),
+                    self.cursor.index()
                 );
-                TokenType::Literal(LiteralToken::String(string))
+                TokenType::Literal(LiteralToken::String {
+                    value: string,
+                    raw: self.cursor.lexeme().to_string(),
+                })
             }
             '\'' => {
                 self.advance()?;
-                let character = self.consume()?;
                 ensure!(
-                    self.consume()? == '\'',
-                    LexerError::InvalidCharacterLiteral(self.line_no, self.col_no)
+                    self.has_next() && !matches!(self.curr_char(), '\'' | '\n'),
+                    LexerError::InvalidCharacterLiteral(start.1.line, start.1.col)
                 );
-                debug!(
-                    "Collected character literal: {:?} at {}:{}[{}] to {}:{}[{}]",
-                    character, start.1, start.2, start.0, self.line_no, self.col_no, self.index
+                let character = match self.consume()? {
+                    '\\' => self
+                        .collect_escape()
+                        .inspect_err(|_| self.skip_character_literal())?,
+                    c => c,
+                };
+                ensure!(
+                    self.curr_char() == '\'',
+                    LexerError::InvalidCharacterLiteral(start.1.line, start.1.col)
                 );
-                TokenType::Literal(LiteralToken::Character(character))
-            }
-            '#' => {
+                self.advance()?;
                 debug!(
-                    "Found comment at {}:{}[{}]",
-                    self.line_no, self.col_no, self.index
+                    "Collected character literal: {:?} at {}[{}] to {}[{}]",
+                    character,
+                    start.1,
+                    start.0,
+                    self.cursor.line_col(),
+                    self.cursor.index()
                 );
-                self.advance_eol()?;
-                return self.get_next_token();
+                TokenType::Literal(LiteralToken::Character {
+                    value: character,
+                    raw: self.cursor.lexeme().to_string(),
+                })
+            }
+            _ => {
+                let c = self.consume()?;
+                TokenType::from_char(c, Some(self.curr_char()))
             }
-            _ => TokenType::from_char(self.consume()?, Some(self.curr_char)),
+        };
+
+        if let TokenType::Syntax(syntax) = &token_type {
+            self.advance_n(syntax.length() - 1)?;
         }
-        .at(start.0, start.1, start.2);
 
-        if let TokenType::Unknown(_) = token.token_type() {
-            bail!(LexerError::UnknownToken(
-                token,
-                self.index,
-                self.line_no,
-                self.col_no
-            ))
+        let mut token = self.finish_token(token_type);
+        if (token.is_identifier() || token.is_keyword()) && token.text() != self.cursor.lexeme() {
+            token.set_raw(self.cursor.lexeme().to_string());
         }
 
-        if let TokenType::Syntax(_) = token.token_type() {
-            self.advance_n(token.length() - 1)?;
+        if let TokenType::Unknown(_) = token.token_type() {
+            bail!(LexerError::UnknownToken(token))
         }
 
         trace!("Returning token {:?}", token);
@@ -437,8 +941,627 @@ impl Lexer {
     }
 }
 
+fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\r' | '\x0B' | '\x0C')
+}
+
 impl Default for Lexer {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::{Diagnostic, Renderer};
+    use crate::lex::types::{KeywordToken, SyntaxToken};
+    use crate::source::LineIndex;
+    use proptest::prelude::*;
+
+    #[test]
+    fn tracks_byte_offsets_and_char_columns() {
+        let tokens = Lexer::new()
+            .lex("var é\nsprint(\"✓\") x\n".to_string())
+            .unwrap()
+            .tokens;
+        let positions = tokens
+            .iter()
+            .map(|t| (t.index(), t.line_no(), t.col_no()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            positions,
+            vec![
+                (0, 1, 1),   // var
+                (4, 1, 5),   // é
+                (6, 1, 6),   // NL
+                (7, 2, 1),   // sprint
+                (13, 2, 7),  // (
+                (14, 2, 8),  // "✓"
+                (19, 2, 11), // )
+                (21, 2, 13), // x
+                (22, 2, 14), // NL
+                (23, 3, 1),  // EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn folds_consecutive_newlines() {
+        let tokens = Lexer::new().lex("a\n\n\nb".to_string()).unwrap().tokens;
+        let types = tokens
+            .iter()
+            .map(|t| t.token_type().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::IdentifierToken(Symbol::intern("a")),
+                TokenType::NL,
+                TokenType::IdentifierToken(Symbol::intern("b")),
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn recovers_from_every_error() {
+        let source = "var $x\nc : 'ab'\nd : ''\nsprint(\"oops\n";
+        let (stream, errors, _) = Lexer::new().lex_recovering(source);
+        let errors = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert_eq!(errors[0], "Invalid token \"$\" at 1:5");
+        assert_eq!(errors[1], "Invalid character literal at 2:5");
+        assert_eq!(errors[2], "Invalid character literal at 3:5");
+        assert_eq!(errors[3], "Invalid string literal at 4:9");
+
+        let types = stream
+            .tokens
+            .iter()
+            .map(|t| t.token_type().clone())
+            .collect::<Vec<_>>();
+        let ident = |s: &str| TokenType::IdentifierToken(Symbol::intern(s));
+        let error = |s: &str| TokenType::Error(s.to_string());
+        assert_eq!(
+            types,
+            vec![
+                TokenType::Keyword(KeywordToken::Var),
+                error("$"),
+                ident("x"),
+                TokenType::NL,
+                ident("c"),
+                TokenType::Syntax(SyntaxToken::Assign),
+                error("'ab'"),
+                TokenType::NL,
+                ident("d"),
+                TokenType::Syntax(SyntaxToken::Assign),
+                error("''"),
+                TokenType::NL,
+                ident("sprint"),
+                TokenType::Syntax(SyntaxToken::LParen),
+                error("\"oops\n"),
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_escape_sequences() {
+        let source = r#""a\n\t\\\"\'\0\x41\u{1F600}" '\n' '\x7f' '\u{e9}'"#;
+        let literals = Lexer::new()
+            .lex_str(source)
+            .unwrap()
+            .tokens
+            .into_iter()
+            .filter_map(|t| match t.token_type() {
+                TokenType::Literal(l) => Some(l.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            literals,
+            vec![
+                LiteralToken::String {
+                    value: "a\n\t\\\"'\0A\u{1F600}".to_string(),
+                    raw: r#""a\n\t\\\"\'\0\x41\u{1F600}""#.to_string(),
+                },
+                LiteralToken::Character {
+                    value: '\n',
+                    raw: r"'\n'".to_string(),
+                },
+                LiteralToken::Character {
+                    value: '\x7f',
+                    raw: r"'\x7f'".to_string(),
+                },
+                LiteralToken::Character {
+                    value: 'é',
+                    raw: r"'\u{e9}'".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_escapes() {
+        let source = r#"s : "a\qb\x4" c : '\x80' d : '\u{110000}' e : "\u{41" f"#;
+        let (stream, errors, _) = Lexer::new().lex_recovering(source);
+        let errors = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                r"Unknown escape sequence \q at 1:7",
+                r#"Invalid hex escape \x80 at 1:20: expected two hex digits no greater than \x7F"#,
+                r#"Invalid unicode escape \u{110000} at 1:31: expected \u{...} with 1 to 6 hex digits naming a unicode scalar value"#,
+                r#"Invalid unicode escape \u{41 at 1:48: expected \u{...} with 1 to 6 hex digits naming a unicode scalar value"#,
+            ]
+        );
+        let identifiers = stream
+            .get_identifiers()
+            .tokens
+            .iter()
+            .map(|t| t.token_type().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(identifiers.len(), 5, "{:?}", identifiers);
+    }
+
+    #[test]
+    fn lexes_integer_literals() {
+        let source = "0 007 1_000 0xFF_ff 0b1010 0o17 0x_1 9223372036854775808";
+        let tokens = Lexer::new().lex_str(source).unwrap().tokens;
+        let integers = tokens
+            .iter()
+            .filter_map(|t| match t.token_type() {
+                TokenType::Literal(LiteralToken::Integer { value, raw }) => {
+                    assert_eq!(t.length(), raw.len());
+                    Some(*value)
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(integers, vec![0, 7, 1000, 0xFFFF, 10, 15, 1, 1 << 63]);
+    }
+
+    #[test]
+    fn reports_invalid_integer_literals() {
+        let source = "0x 0b102 12ab\n 9223372036854775809 0xFFFFFFFFFFFFFFFFF";
+        let (stream, errors, _) = Lexer::new().lex_recovering(source);
+        let errors = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                "Invalid integer literal 0x at 1:1",
+                "Invalid integer literal 0b102 at 1:4",
+                "Invalid integer literal 12ab at 1:10",
+                "Integer literal 9223372036854775809 at 2:2 does not fit in a 64-bit signed integer",
+                "Integer literal 0xFFFFFFFFFFFFFFFFF at 2:22 does not fit in a 64-bit signed integer",
+            ]
+        );
+        assert_eq!(stream.tokens.iter().filter(|t| t.is_error()).count(), 5);
+    }
+
+    #[test]
+    fn tokens_span_their_source_text() {
+        let mut sources = SourceMap::new();
+        sources.add_file("a.t", "var a\n");
+        let b = sources.add_file("b.t", "x : \"tab\\t\" + 007 >= '\\u{e9}'");
+        let tokens = Lexer::new().lex_source_file(&sources, b).unwrap().tokens;
+        let texts = tokens
+            .iter()
+            .map(|t| {
+                assert_eq!(t.span().file, b);
+                sources.snippet(t.span()).unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            texts,
+            vec!["x", ":", "\"tab\\t\"", "+", "007", ">=", "'\\u{e9}'", ""]
+        );
+    }
+
+    #[test]
+    fn lossless_round_trips_examples() {
+        let examples = concat!(env!("CARGO_MANIFEST_DIR"), "/examples");
+        let mut checked = 0;
+        for entry in std::fs::read_dir(examples).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "t") {
+                continue;
+            }
+            let source = std::fs::read_to_string(&path).unwrap();
+            let stream = Lexer::new().lossless(true).lex_str(&source).unwrap();
+            assert_eq!(stream.to_source(), source, "{}", path.display());
+            checked += 1;
+        }
+        assert!(checked > 0);
+    }
+
+    #[test]
+    fn attaches_trivia_to_lines() {
+        let source = "  a : 1 # one\r\n\n\t# two\n\tb  \n";
+        let stream = Lexer::new().lossless(true).lex_str(source).unwrap();
+        assert_eq!(stream.to_source(), source);
+        let full = stream
+            .tokens
+            .iter()
+            .map(|t| t.full_text())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            full,
+            vec![
+                "  a ",
+                ": ",
+                "1 # one\r",
+                "\n\n\t# two\n",
+                "\tb  ",
+                "\n",
+                ""
+            ]
+        );
+    }
+
+    #[test]
+    fn iterates_lazily_and_stops_at_errors() {
+        let lexer = Lexer::new();
+        let mut tokens = lexer.tokens("a\n\n\nb $ c");
+        let ident = |s: &str| TokenType::IdentifierToken(Symbol::intern(s));
+        assert_eq!(tokens.next().unwrap().unwrap().token_type(), &ident("a"));
+        let nl = tokens.next().unwrap().unwrap();
+        assert_eq!((nl.token_type(), nl.line_no()), (&TokenType::NL, 3));
+        assert_eq!(tokens.next().unwrap().unwrap().token_type(), &ident("b"));
+        assert!(matches!(
+            tokens.next(),
+            Some(Err(LexerError::UnknownToken(..)))
+        ));
+        assert!(tokens.next().is_none());
+
+        let lexed = lexer.tokens("x\n\ny").collect::<Result<TokenStream, _>>();
+        let collected = Lexer::new().lex_str("x\n\ny").unwrap();
+        assert_eq!(lexed.unwrap().tokens, collected.tokens);
+    }
+
+    // If this fails, the JSON shape changed: update the docs on `to_json` and
+    // bump `TOKEN_JSON_VERSION`.
+    #[test]
+    fn json_shape_is_stable() {
+        let stream = Lexer::new()
+            .lossless(true)
+            .lex_str("i : 'x' # c\n")
+            .unwrap();
+        let json = stream.to_json();
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"version":1,"tokens":["#,
+                r#"{"token_type":{"IdentifierToken":"i"},"span":{"file":0,"start":0,"end":1},"line_no":1,"col_no":1,"trailing_trivia":[{"Whitespace":" "}]},"#,
+                r#"{"token_type":{"Syntax":"Assign"},"span":{"file":0,"start":2,"end":3},"line_no":1,"col_no":3,"trailing_trivia":[{"Whitespace":" "}]},"#,
+                r##"{"token_type":{"Literal":{"Character":{"value":"x","raw":"'x'"}}},"span":{"file":0,"start":4,"end":7},"line_no":1,"col_no":5,"trailing_trivia":[{"Whitespace":" "},{"Comment":"# c"}]},"##,
+                r#"{"token_type":"NL","span":{"file":0,"start":11,"end":12},"line_no":1,"col_no":12},"#,
+                r#"{"token_type":"Eof","span":{"file":0,"start":12,"end":12},"line_no":2,"col_no":1}"#,
+                r#"]}"#
+            )
+        );
+        assert_eq!(TokenStream::from_json(&json).unwrap().tokens, stream.tokens);
+    }
+
+    #[test]
+    fn json_round_trips_examples_and_checks_version() {
+        let source = include_str!("../../examples/sq.t");
+        let stream = Lexer::new().lex_str(source).unwrap();
+        let parsed = TokenStream::from_json(&stream.to_json()).unwrap();
+        assert_eq!(parsed.tokens, stream.tokens);
+
+        let error = TokenStream::from_json(r#"{"version":0,"tokens":[]}"#).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Unsupported token JSON version 0, expected {}",
+                TOKEN_JSON_VERSION
+            )
+        );
+    }
+
+    #[test]
+    fn reader_matches_str() {
+        let source = "fun sq(n) {\n    return .n * .n\n}\n";
+        let crlf = format!("\u{FEFF}{}", source.replace('\n', "\r\n"));
+        let from_str = Lexer::new().lex_str(source).unwrap().tokens;
+        let from_reader = Lexer::new().lex_reader(crlf.as_bytes()).unwrap().tokens;
+        assert_eq!(from_str, from_reader);
+
+        let mut sources = SourceMap::new();
+        let file = sources.add_file("sq.t", crlf);
+        let from_map = Lexer::new().lex_source_file(&sources, file).unwrap().tokens;
+        assert_eq!(from_map, from_reader);
+    }
+
+    #[test]
+    fn identifiers_follow_uax31() {
+        let tokens = Lexer::new().lex_str("my_var _x café ĸ1\n").unwrap().tokens;
+        let idents = tokens
+            .iter()
+            .filter(|t| t.is_identifier())
+            .map(|t| t.text().into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(idents, vec!["my_var", "_x", "café", "ĸ1"]);
+        // A superscript digit is alphanumeric but not XID_Continue.
+        assert!(Lexer::new().lex_str("x²").is_err());
+        assert!(Lexer::new().lex_str("٣x").is_err());
+    }
+
+    #[test]
+    fn normalizes_identifiers_to_nfc() {
+        let composed = "caf\u{E9}";
+        let decomposed = "cafe\u{301}";
+        let tokens = Lexer::new()
+            .lex_str(&format!("{} {}", composed, decomposed))
+            .unwrap()
+            .tokens;
+        assert_eq!(tokens[0].token_type(), tokens[1].token_type());
+        assert_eq!(tokens[1].token_type().text(), composed);
+        // The token keeps its spelling so that lossless output matches the source.
+        assert_eq!(tokens[1].text(), decomposed);
+        assert_eq!(tokens[1].span().len(), decomposed.len());
+    }
+
+    #[test]
+    fn warns_about_mixed_script_identifiers() {
+        // The `а` in `pаypal` is Cyrillic.
+        let source = "pаypal café ΑΒΓ x\n";
+        let expected = vec![LexerWarning::MixedScriptIdentifier(
+            "pаypal".to_string(),
+            1,
+            1,
+            "Latin, Cyrillic".to_string(),
+        )];
+        let mut tokens = Lexer::new().tokens(source);
+        assert!(tokens.by_ref().all(|t| t.is_ok()));
+        assert_eq!(tokens.take_warnings(), expected);
+
+        let (_, errors, warnings) = Lexer::new().lex_recovering("var pаypal : $\n");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            warnings.iter().map(|w| w.code()).collect::<Vec<_>>(),
+            vec!["W0001"]
+        );
+    }
+
+    #[test]
+    fn lexes_block_and_line_comments() {
+        let source = "a #[ one #[ two ]# still one ]# b\n#[\n]#c # to the end";
+        let kinds = Lexer::new()
+            .lex_str(source)
+            .unwrap()
+            .tokens
+            .iter()
+            .map(|t| t.text().into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, vec!["a", "b", "\n", "c", ""]);
+
+        let (stream, errors, _) = Lexer::new().lex_recovering("a #[ #[ ]#\nb");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].to_string(), "Invalid comment at 1:3");
+        assert_eq!(stream.tokens[1].text(), "#[ #[ ]#\nb");
+    }
+
+    #[test]
+    fn skips_thousands_of_comments() {
+        let tokens = Lexer::new()
+            .lex_str(&"#[]#".repeat(100_000))
+            .unwrap()
+            .tokens;
+        assert_eq!(tokens.len(), 1);
+        let source = format!("a{}b", "#[ x ]# # y\n".repeat(100_000));
+        let kinds = Lexer::new()
+            .lex_str(&source)
+            .unwrap()
+            .tokens
+            .iter()
+            .map(Token::kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Identifier,
+                TokenKind::NL,
+                TokenKind::Identifier,
+                TokenKind::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn lexes_doc_comments() {
+        let source = "## Squares n.\n##\nfun sq(n) # not a doc\n";
+        let tokens = Lexer::new().lossless(true).lex_str(source).unwrap().tokens;
+        assert_eq!(
+            tokens[0].token_type(),
+            &TokenType::DocComment {
+                text: "Squares n.".to_string(),
+                raw: "## Squares n.".to_string(),
+            }
+        );
+        assert_eq!(tokens[1].kind(), TokenKind::NL);
+        assert!(tokens[2].is_doc_comment());
+        assert_eq!(tokens[4].kind(), TokenKind::Keyword(KeywordToken::Fun));
+        assert_eq!(
+            tokens.iter().map(Token::full_text).collect::<String>(),
+            source
+        );
+    }
+
+    // Checks the invariants that must hold for any input: lexing ends, spans
+    // cover the source in order and on char boundaries, and every error is
+    // located inside the source.
+    fn check_invariants(source: &str) -> Result<(), TestCaseError> {
+        let (stream, errors, _) = Lexer::new().lossless(true).lex_recovering(source);
+        let tokens = &stream.tokens;
+        prop_assert!(tokens.len() <= source.len() + 1);
+        prop_assert_eq!(tokens.last().map(Token::kind), Some(TokenKind::Eof));
+        let mut end = 0;
+        for token in tokens {
+            let span = token.span();
+            prop_assert!(end <= span.start && span.end <= source.len(), "{:?}", token);
+            prop_assert!(source.is_char_boundary(span.start) && source.is_char_boundary(span.end));
+            end = span.end;
+        }
+        prop_assert_eq!(stream.to_source(), source);
+
+        let index = LineIndex::new(source);
+        for error in &errors {
+            let (line_no, col_no) = error_position(error);
+            let line = index.line_range(line_no);
+            prop_assert!(line.is_some(), "{} is past the last line", error);
+            let line_len = source[line.unwrap()].chars().count();
+            prop_assert!(
+                col_no >= 1 && col_no <= line_len + 1,
+                "{} is past the end of its line",
+                error
+            );
+        }
+
+        let mut sources = SourceMap::new();
+        let file = sources.add_file("fuzz.t", source);
+        let stored = sources.get(file).unwrap();
+        // The map normalises line endings, so render the errors of its text.
+        // Rendering takes time in the length of the line, which can be all of a
+        // long input, so only the first errors are rendered.
+        let (_, stored_errors, _) = Lexer::new().lex_recovering(stored.source());
+        for error in stored_errors.iter().take(64) {
+            Renderer::new().render(&error.report(), stored);
+        }
+
+        let strict = Lexer::new().tokens(source).collect::<Result<Vec<_>, _>>();
+        prop_assert_eq!(strict.is_ok(), errors.is_empty());
+        let extended = Lexer::with_config(LexerConfig::extended()).lex_recovering(source);
+        prop_assert_eq!(
+            extended.0.tokens.last().map(Token::kind),
+            Some(TokenKind::Eof)
+        );
+        Ok(())
+    }
+
+    // Inputs that used to crash, hang or break the invariants.
+    #[test]
+    fn regressions() {
+        for source in [
+            "",
+            "a",
+            "\"abc",
+            "'",
+            "'\\",
+            "\"\\",
+            "#[",
+            "e\u{301}",
+            "x # comment without a newline",
+        ] {
+            check_invariants(source).unwrap();
+        }
+        // Used to overflow the stack, one frame per comment.
+        let insignificant = LexerConfig {
+            significant_newlines: false,
+            ..LexerConfig::extended()
+        };
+        for piece in ["#[]#", "# c\n", "// c\n", "#[ ]# ", "\n"] {
+            let source = piece.repeat(20_000);
+            check_invariants(&source).unwrap();
+            let (stream, errors, _) =
+                Lexer::with_config(insignificant.clone()).lex_recovering(&source);
+            assert!(errors.is_empty(), "{:?}: {:?}", piece, errors);
+            assert!(stream.tokens.len() <= 2, "{:?}", piece);
+        }
+    }
+
+    fn error_position(error: &LexerError) -> (usize, usize) {
+        match error {
+            LexerError::UnknownEscape(_, l, c)
+            | LexerError::InvalidHexEscape(_, l, c)
+            | LexerError::InvalidUnicodeEscape(_, l, c)
+            | LexerError::InvalidIntegerLiteral(_, l, c)
+            | LexerError::IntegerOverflow(_, l, c) => (*l, *c),
+            LexerError::InvalidStringLiteral(l, c)
+            | LexerError::InvalidCharacterLiteral(l, c)
+            | LexerError::InvalidComment(l, c)
+            | LexerError::InvalidEOF(l, c)
+            | LexerError::Unknown(l, c) => (*l, *c),
+            LexerError::UnknownToken(token) => (token.line_no(), token.col_no()),
+        }
+    }
+
+    // Pieces of source that exercise every branch of the lexer, so random
+    // concatenations of them reach far more states than random text.
+    const PIECES: &[&str] = &[
+        "var",
+        "fun",
+        "x",
+        "_",
+        "é",
+        "e\u{301}",
+        "pаy",
+        "0",
+        "0x",
+        "0b",
+        "9",
+        "_1",
+        "99999999999999999999",
+        " ",
+        "\t",
+        "\r",
+        "\n",
+        "(",
+        ")",
+        "{",
+        "}",
+        ":",
+        ".",
+        "-",
+        ">",
+        ">=",
+        "=",
+        "!",
+        "\"",
+        "'",
+        "\\",
+        "\\x",
+        "\\u{",
+        "7F",
+        "}",
+        "#",
+        "##",
+        "#[",
+        "]#",
+        "/",
+        "$",
+        "😀",
+        "\0",
+    ];
+
+    proptest! {
+        #[test]
+        fn lexes_any_text(source in any::<String>()) {
+            check_invariants(&source)?;
+        }
+
+        #[test]
+        fn reads_any_bytes(bytes in any::<Vec<u8>>()) {
+            let _ = Lexer::new().lex_reader(bytes.as_slice());
+        }
+
+        #[test]
+        fn lexes_any_mix_of_tokens(
+            pieces in prop_oneof![
+                7 => prop::collection::vec(prop::sample::select(PIECES), 0..64),
+                1 => prop::collection::vec(prop::sample::select(PIECES), 0..5000),
+            ]
+        ) {
+            check_invariants(&pieces.concat())?;
+        }
+
+        #[test]
+        fn lexes_long_runs_of_the_same_pieces(
+            pieces in prop::collection::vec(prop::sample::select(PIECES), 1..4),
+            times in 0..5000usize,
+        ) {
+            check_invariants(&pieces.concat().repeat(times))?;
+        }
+    }
+}
@@ -1,8 +1,16 @@
+pub(crate) mod config;
 pub(crate) mod consts;
+pub(crate) mod cursor;
+pub(crate) mod diagnostic;
 pub(crate) mod lexer;
+pub(crate) mod reader;
+pub(crate) mod relex;
 pub(crate) mod token;
 pub(crate) mod types;
-pub(crate) mod reader;
-pub(crate) mod cursor;
 
-pub use lexer::Lexer;
+pub use config::LexerConfig;
+pub use lexer::{Lexer, LexerError, LexerWarning, TokenStream, Tokens, TOKEN_JSON_VERSION};
+pub use reader::{normalize, SourceReader};
+pub use relex::TextEdit;
+pub use token::{Token, TokenKind, TokenType, Trivia};
+pub use types::{KeywordToken, LiteralToken, SyntaxToken};
@@ -0,0 +1,151 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, StdinLock};
+use std::path::Path;
+
+const BOM: char = '\u{FEFF}';
+
+/// Drops a leading byte order mark and normalises CRLF / lone CR line endings
+/// to `\n`, exactly as `SourceReader` does while streaming.
+pub fn normalize(text: &str) -> Cow<'_, str> {
+    let text = text.strip_prefix(BOM).unwrap_or(text);
+    if !text.contains('\r') {
+        return Cow::Borrowed(text);
+    }
+    let mut normalized = String::with_capacity(text.len());
+    push_normalized(text, &mut normalized);
+    Cow::Owned(normalized)
+}
+
+fn push_normalized(text: &str, out: &mut String) {
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\r' {
+            chars.next_if_eq(&'\n');
+            out.push('\n');
+        } else {
+            out.push(c);
+        }
+    }
+}
+
+/// Streams the characters of a source file to the lexer one line at a time.
+///
+/// A leading UTF-8 byte order mark is dropped and CRLF / lone CR line endings
+/// are normalised to `\n`, so the lexer only ever sees `\n`. Reading stops at
+/// the first I/O or UTF-8 error, which is kept until `take_error` is called.
+pub struct SourceReader<R: BufRead> {
+    reader: R,
+    bytes: Vec<u8>,
+    line: String,
+    pos: usize,
+    first_line: bool,
+    error: Option<io::Error>,
+}
+
+impl<R: BufRead> SourceReader<R> {
+    pub fn new(reader: R) -> Self {
+        SourceReader {
+            reader,
+            bytes: Vec::new(),
+            line: String::new(),
+            pos: 0,
+            first_line: true,
+            error: None,
+        }
+    }
+
+    /// Returns the error that stopped the reader, if any.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    // Reads and decodes the next line. Returns false once the input is exhausted.
+    fn fill_line(&mut self) -> bool {
+        if self.error.is_some() {
+            return false;
+        }
+        self.bytes.clear();
+        match self.reader.read_until(b'\n', &mut self.bytes) {
+            Ok(0) => return false,
+            Ok(_) => {}
+            Err(e) => {
+                self.error = Some(e);
+                return false;
+            }
+        }
+        let mut line = match std::str::from_utf8(&self.bytes) {
+            Ok(line) => line,
+            Err(e) => {
+                self.error = Some(io::Error::new(io::ErrorKind::InvalidData, e));
+                return false;
+            }
+        };
+        if self.first_line {
+            self.first_line = false;
+            line = line.strip_prefix(BOM).unwrap_or(line);
+        }
+        self.line.clear();
+        self.pos = 0;
+        push_normalized(line, &mut self.line);
+        true
+    }
+}
+
+impl SourceReader<BufReader<File>> {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(SourceReader::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl SourceReader<StdinLock<'static>> {
+    pub fn stdin() -> Self {
+        SourceReader::new(io::stdin().lock())
+    }
+}
+
+impl<R: BufRead> Iterator for SourceReader<R> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        loop {
+            if let Some(c) = self.line[self.pos..].chars().next() {
+                self.pos += c.len_utf8();
+                return Some(c);
+            }
+            if !self.fill_line() {
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_bom_and_normalises_line_endings() {
+        let input = "\u{FEFF}a\r\nb\rc\n\u{FEFF}d".as_bytes();
+        let chars = SourceReader::new(input).collect::<String>();
+        assert_eq!(chars, "a\nb\nc\n\u{FEFF}d");
+    }
+
+    #[test]
+    fn normalize_matches_reader() {
+        for text in ["plain\n", "\u{FEFF}a\r\nb\rc\r", "\r\r\n\n"] {
+            let streamed = SourceReader::new(text.as_bytes()).collect::<String>();
+            assert_eq!(normalize(text), streamed);
+        }
+        assert!(matches!(normalize("a\nb"), Cow::Borrowed("a\nb")));
+    }
+
+    #[test]
+    fn stops_on_invalid_utf8() {
+        let input: &[u8] = b"ok\n\xFF\xFE\n";
+        let mut reader = SourceReader::new(input);
+        assert_eq!(reader.by_ref().collect::<String>(), "ok\n");
+        let error = reader.take_error().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::lex::lexer::{Lexer, TokenStream};
+use crate::lex::token::{Token, TokenKind};
+use crate::source::{LineCol, LineIndex, WideEncoding};
+use std::ops::Range;
+
+/// Replaces the bytes in `range` of a text with `text`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, text: impl Into<String>) -> Self {
+        TextEdit {
+            range,
+            text: text.into(),
+        }
+    }
+
+    pub fn insert(offset: usize, text: impl Into<String>) -> Self {
+        TextEdit::new(offset..offset, text)
+    }
+
+    pub fn delete(range: Range<usize>) -> Self {
+        TextEdit::new(range, "")
+    }
+
+    pub fn apply(&self, source: &str) -> String {
+        let mut edited = source.to_string();
+        edited.replace_range(self.range.clone(), &self.text);
+        edited
+    }
+
+    // How far the edit moves the text after it.
+    fn delta(&self) -> isize {
+        self.text.len() as isize - self.range.len() as isize
+    }
+}
+
+impl Lexer {
+    /// Relexes `old` after `edit`, giving the same tokens as lexing the edited
+    /// text from scratch with error recovery.
+    ///
+    /// `old` must hold every token of a lossless lex, as the text is rebuilt
+    /// from it, and the result is lossless too. Only the lines from the one
+    /// before the edit up to the first newline after it that starts the same
+    /// token as before are rescanned. That is usually a line or two, but an
+    /// edit that opens a string or block comment is rescanned to where it ends,
+    /// which may be the end of the file. The tokens after are reused with their
+    /// spans and lines shifted.
+    pub fn relex(&self, old: &TokenStream, edit: TextEdit) -> TokenStream {
+        let tokens = &old.tokens;
+        // NL tokens always start with an unchanged '\n' that nothing before
+        // them looks past, so lexing can restart there. Consecutive NLs are
+        // folded, so the token before is never another NL to fold into.
+        let restart = tokens
+            .iter()
+            .rposition(|t| t.kind() == TokenKind::NL && t.index() < edit.range.start)
+            .unwrap_or(0);
+        let (start, origin) = match restart.checked_sub(1).map(|i| &tokens[i]) {
+            Some(prev) => {
+                let text = prev.text().into_owned()
+                    + &prev
+                        .trailing_trivia()
+                        .iter()
+                        .map(|t| t.text())
+                        .collect::<String>();
+                let origin = LineIndex::new(&text).wide_line_col_from(
+                    LineCol::new(prev.line_no(), prev.col_no()),
+                    WideEncoding::Utf32,
+                    text.len(),
+                );
+                (prev.index() + text.len(), origin)
+            }
+            None => (0, LineCol::new(1, 1)),
+        };
+
+        let mut offset = start;
+        let old_chars = tokens[restart..]
+            .iter()
+            .flat_map(|t| t.full_text().chars().collect::<Vec<_>>())
+            .map(move |c| {
+                offset += c.len_utf8();
+                (offset - c.len_utf8(), c)
+            });
+        let (edit_start, edit_end) = (edit.range.start, edit.range.end);
+        let chars = old_chars
+            .clone()
+            .take_while(move |&(o, _)| o < edit_start)
+            .map(|(_, c)| c)
+            .chain(edit.text.chars())
+            .chain(
+                old_chars
+                    .skip_while(move |&(o, _)| o < edit_end)
+                    .map(|(_, c)| c),
+            );
+
+        let delta = edit.delta();
+        let edited_end = edit.range.start + edit.text.len();
+        let mut relexed = tokens[..restart].to_vec();
+        let mut old_index = restart;
+        for token in self
+            .tokens_at(chars, true, start, origin)
+            .recovering()
+            .flatten()
+        {
+            // Once a newline after the edit starts an NL token that was there
+            // before, the rest of the text lexes exactly as it did.
+            if token.kind() == TokenKind::NL && token.index() >= edited_end {
+                let index = token.index().wrapping_add_signed(-delta);
+                while old_index < tokens.len() && tokens[old_index].index() < index {
+                    old_index += 1;
+                }
+                if let Some(old_token) = tokens
+                    .get(old_index)
+                    .filter(|t| t.kind() == TokenKind::NL && t.index() == index)
+                {
+                    let lines = token.line_no() as isize - old_token.line_no() as isize;
+                    relexed.push(token);
+                    relexed.extend(
+                        tokens[old_index + 1..]
+                            .iter()
+                            .map(|t| shifted(t, delta, lines)),
+                    );
+                    debug!(
+                        "Relexed {} of {} tokens",
+                        relexed.len() + old_index + 1 - tokens.len(),
+                        relexed.len()
+                    );
+                    return TokenStream { tokens: relexed };
+                }
+            }
+            relexed.push(token);
+        }
+        debug!("Relexed to the end of the file");
+        TokenStream { tokens: relexed }
+    }
+}
+
+fn shifted(token: &Token, offset: isize, lines: isize) -> Token {
+    let mut token = token.clone();
+    token.shift(offset, lines);
+    token
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const FRAGMENTS: &[&str] = &[
+        "var ", "fun ", "x", "é", "12", " ", "\t", "\n", "\n\n", "(", ")", "{", "}", ":", ".",
+        ">=", "\"", "'", "\\", "#", "##", "#[", "]#", "'a'", "\"s\"", "$",
+    ];
+
+    fn lex(source: &str) -> Vec<Token> {
+        Lexer::new().lossless(true).lex_recovering(source).0.tokens
+    }
+
+    fn check(source: &str, edit: TextEdit) -> Result<String, TestCaseError> {
+        let edited = edit.apply(source);
+        let old = TokenStream {
+            tokens: lex(source),
+        };
+        let relexed = Lexer::new().relex(&old, edit).tokens;
+        prop_assert_eq!(
+            relexed,
+            lex(&edited),
+            "editing {:?} to {:?}",
+            source,
+            edited
+        );
+        Ok(edited)
+    }
+
+    fn source() -> impl Strategy<Value = String> {
+        prop::collection::vec(prop::sample::select(FRAGMENTS), 0..40).prop_map(|f| f.concat())
+    }
+
+    // A char-aligned edit of `source`, from proportions of its length.
+    fn edit_of(source: &str, start: f64, len: f64, text: Vec<&str>) -> TextEdit {
+        let bounds = source
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([source.len()])
+            .collect::<Vec<_>>();
+        let start = (start * (bounds.len() - 1) as f64) as usize;
+        let end = start + (len * (bounds.len() - 1 - start) as f64) as usize;
+        TextEdit::new(bounds[start]..bounds[end], text.concat())
+    }
+
+    fn edit() -> impl Strategy<Value = (f64, f64, Vec<&'static str>)> {
+        (
+            0.0..=1.0,
+            prop_oneof![Just(0.0), 0.0..0.3],
+            prop::collection::vec(prop::sample::select(FRAGMENTS), 0..3),
+        )
+    }
+
+    #[test]
+    fn opening_and_closing_strings_and_comments() {
+        let source = "var a\nx : 1\n\ny : \"s\"\nfun f() {\n}\n";
+        check(source, TextEdit::insert(6, "\"")).unwrap();
+        check(source, TextEdit::insert(6, "#[")).unwrap();
+        check(source, TextEdit::delete(19..20)).unwrap();
+        check("a #[ b\nc\n", TextEdit::insert(8, "]#")).unwrap();
+        check("a # b\nc\n", TextEdit::delete(5..6)).unwrap();
+        check("a\nb\n", TextEdit::insert(1, "x")).unwrap();
+        check("", TextEdit::insert(0, "var x\n")).unwrap();
+    }
+
+    #[test]
+    fn reuses_tokens_after_the_edit() {
+        let source = "var a\nvar b\nvar c\nvar d\n";
+        let old = Lexer::new().lossless(true).lex_str(source).unwrap();
+        let relexed = Lexer::new().relex(&old, TextEdit::new(10..11, "bb"));
+        assert_eq!(relexed.tokens, lex("var a\nvar bb\nvar c\nvar d\n"));
+        let last = relexed.tokens.iter().rev().nth(2).unwrap();
+        assert_eq!(
+            (last.text().as_ref(), last.index(), last.line_no()),
+            ("d", 23, 4)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn matches_a_full_relex(source in source(), (start, len, text) in edit()) {
+            check(&source, edit_of(&source, start, len, text))?;
+        }
+
+        #[test]
+        fn matches_a_full_relex_after_many_edits(
+            source in source(),
+            edits in prop::collection::vec(edit(), 1..8),
+        ) {
+            let mut source = source;
+            for (start, len, text) in edits {
+                source = check(&source, edit_of(&source, start, len, text))?;
+            }
+        }
+    }
+}
@@ -1,23 +1,57 @@
 use crate::lex::types::{KeywordToken, LiteralToken, SyntaxToken};
+use crate::source::Span;
+use crate::symbol::Symbol;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
 
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub enum TokenType {
     Keyword(KeywordToken),
     Syntax(SyntaxToken),
-    IdentifierToken(String),
+    IdentifierToken(Symbol),
     Literal(LiteralToken),
     Unknown(char),
+    /// The source text of a span the lexer could not make sense of. Only
+    /// produced when lexing with error recovery.
+    Error(String),
+    /// A doc comment line. `text` is the comment without its introducer and
+    /// the first space after it.
+    DocComment {
+        text: String,
+        raw: String,
+    },
     Eof,
     NL,
 }
 
 impl TokenType {
-    pub fn at(self, index: usize, line_no: usize, col_no: usize) -> Token {
+    pub fn at(self, span: Span, line_no: usize, col_no: usize) -> Token {
         Token {
             token_type: self,
-            index,
+            span,
             line_no,
             col_no,
+            raw: None,
+            leading_trivia: Vec::new(),
+            trailing_trivia: Vec::new(),
+        }
+    }
+
+    /// The source text of the token.
+    pub fn text(&self) -> Cow<'_, str> {
+        match self {
+            TokenType::Keyword(k) => Cow::Borrowed(k.as_str()),
+            TokenType::Syntax(s) => Cow::Borrowed(s.as_str()),
+            TokenType::IdentifierToken(s) => Cow::Borrowed(s.as_str()),
+            TokenType::Literal(LiteralToken::Integer { raw, .. })
+            | TokenType::Literal(LiteralToken::String { raw, .. })
+            | TokenType::Literal(LiteralToken::Character { raw, .. }) => Cow::Borrowed(raw),
+            TokenType::Unknown(c) => Cow::Owned(c.to_string()),
+            TokenType::Error(s) => Cow::Borrowed(s),
+            TokenType::DocComment { raw, .. } => Cow::Borrowed(raw),
+            TokenType::Eof => Cow::Borrowed(""),
+            TokenType::NL => Cow::Borrowed("\n"),
         }
     }
 
@@ -26,19 +60,108 @@ impl TokenType {
             .map(TokenType::Syntax)
             .unwrap_or_else(|| TokenType::Unknown(c))
     }
+
+    pub fn kind(&self) -> TokenKind {
+        match self {
+            TokenType::Keyword(k) => TokenKind::Keyword(*k),
+            TokenType::Syntax(s) => TokenKind::Syntax(*s),
+            TokenType::IdentifierToken(_) => TokenKind::Identifier,
+            TokenType::Literal(LiteralToken::Integer { .. }) => TokenKind::Integer,
+            TokenType::Literal(LiteralToken::String { .. }) => TokenKind::String,
+            TokenType::Literal(LiteralToken::Character { .. }) => TokenKind::Character,
+            TokenType::Unknown(_) | TokenType::Error(_) => TokenKind::Error,
+            TokenType::DocComment { .. } => TokenKind::DocComment,
+            TokenType::Eof => TokenKind::Eof,
+            TokenType::NL => TokenKind::NL,
+        }
+    }
+}
+
+/// The kind of a token without its value, cheap to copy and compare.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum TokenKind {
+    Keyword(KeywordToken),
+    Syntax(SyntaxToken),
+    Identifier,
+    Integer,
+    String,
+    Character,
+    Error,
+    DocComment,
+    Eof,
+    NL,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Keyword(k) => write!(f, "'{}'", k.as_str()),
+            TokenKind::Syntax(s) => write!(f, "'{}'", s.as_str()),
+            TokenKind::Identifier => f.write_str("identifier"),
+            TokenKind::Integer => f.write_str("integer literal"),
+            TokenKind::String => f.write_str("string literal"),
+            TokenKind::Character => f.write_str("character literal"),
+            TokenKind::Error => f.write_str("invalid token"),
+            TokenKind::DocComment => f.write_str("doc comment"),
+            TokenKind::Eof => f.write_str("end of file"),
+            TokenKind::NL => f.write_str("newline"),
+        }
+    }
 }
 
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Token {
     token_type: TokenType,
-    index: usize,
+    span: Span,
     line_no: usize,
     col_no: usize,
+    // The source text, if it differs from that of `token_type`, as for
+    // identifiers that weren't written in NFC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    raw: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    leading_trivia: Vec<Trivia>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    trailing_trivia: Vec<Trivia>,
+}
+
+/// Source text between tokens. Only kept when lexing in lossless mode.
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Trivia {
+    Whitespace(String),
+    Comment(String),
+    /// A newline whose `NL` token was folded into the next one.
+    Newline,
+}
+
+impl Trivia {
+    pub fn text(&self) -> &str {
+        match self {
+            Trivia::Whitespace(s) | Trivia::Comment(s) => s,
+            Trivia::Newline => "\n",
+        }
+    }
 }
 
 impl Token {
-    pub fn token_type(&self) -> TokenType {
-        self.token_type.clone()
+    pub fn token_type(&self) -> &TokenType {
+        &self.token_type
+    }
+
+    pub fn kind(&self) -> TokenKind {
+        self.token_type.kind()
+    }
+
+    /// The source text of the token.
+    pub fn text(&self) -> Cow<'_, str> {
+        match &self.raw {
+            Some(raw) => Cow::Borrowed(raw),
+            None => self.token_type.text(),
+        }
+    }
+
+    pub(crate) fn set_raw(&mut self, raw: String) {
+        self.raw = Some(raw);
     }
 
     pub fn is_keyword(&self) -> bool {
@@ -52,37 +175,77 @@ impl Token {
     pub fn is_integer_literal(&self) -> bool {
         matches!(
             self.token_type,
-            TokenType::Literal(LiteralToken::Integer(_))
+            TokenType::Literal(LiteralToken::Integer { .. })
         )
     }
 
     pub fn is_string_literal(&self) -> bool {
-        matches!(self.token_type, TokenType::Literal(LiteralToken::String(_)))
+        matches!(
+            self.token_type,
+            TokenType::Literal(LiteralToken::String { .. })
+        )
     }
 
     pub fn is_character_literal(&self) -> bool {
         matches!(
             self.token_type,
-            TokenType::Literal(LiteralToken::Character(_))
+            TokenType::Literal(LiteralToken::Character { .. })
         )
     }
-    
+
+    pub fn is_error(&self) -> bool {
+        matches!(self.token_type, TokenType::Error(_))
+    }
+
+    pub fn is_doc_comment(&self) -> bool {
+        matches!(self.token_type, TokenType::DocComment { .. })
+    }
+
     pub fn length(&self) -> usize {
-        match &self.token_type {
-            TokenType::Keyword(k) => k.length(),
-            TokenType::Syntax(s) => s.length(),
-            TokenType::IdentifierToken(s) => s.len(),
-            TokenType::Literal(LiteralToken::Integer(i)) => i.to_string().len(),
-            TokenType::Literal(LiteralToken::String(s)) => s.len(),
-            TokenType::Literal(LiteralToken::Character(c)) => c.len_utf8(),
-            TokenType::Unknown(c) => c.len_utf8(),
-            TokenType::Eof => 0,
-            TokenType::NL => 1,
-        }
+        self.span.len()
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
     }
 
+    /// Byte offset of the start of the token.
     pub fn index(&self) -> usize {
-        self.index
+        self.span.start
+    }
+
+    /// Trivia between the end of the previous line (or the start of the file) and this token.
+    pub fn leading_trivia(&self) -> &[Trivia] {
+        &self.leading_trivia
+    }
+
+    /// Trivia after this token up to the end of its line.
+    pub fn trailing_trivia(&self) -> &[Trivia] {
+        &self.trailing_trivia
+    }
+
+    /// Moves the token `offset` bytes and `lines` lines further down its file.
+    pub(crate) fn shift(&mut self, offset: isize, lines: isize) {
+        self.span.start = self.span.start.wrapping_add_signed(offset);
+        self.span.end = self.span.end.wrapping_add_signed(offset);
+        self.line_no = self.line_no.wrapping_add_signed(lines);
+    }
+
+    pub(crate) fn leading_trivia_mut(&mut self) -> &mut Vec<Trivia> {
+        &mut self.leading_trivia
+    }
+
+    pub(crate) fn trailing_trivia_mut(&mut self) -> &mut Vec<Trivia> {
+        &mut self.trailing_trivia
+    }
+
+    /// The token's text surrounded by its trivia.
+    pub fn full_text(&self) -> String {
+        let mut text = String::new();
+        text.extend(self.leading_trivia.iter().map(Trivia::text));
+        text.push_str(&self.text());
+        text.extend(self.trailing_trivia.iter().map(Trivia::text));
+        text
     }
 
     pub fn line_no(&self) -> usize {
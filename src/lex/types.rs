@@ -1,4 +1,6 @@
-#[derive(Debug, Eq, PartialEq, Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum KeywordToken {
     Var,
     Fun,
@@ -7,9 +9,12 @@ pub enum KeywordToken {
     Until,
     Loop,
     Return,
+    Const,
+    Import,
 }
 
 impl KeywordToken {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "var" => Some(KeywordToken::Var),
@@ -22,7 +27,21 @@ impl KeywordToken {
             _ => None,
         }
     }
-    
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeywordToken::Var => "var",
+            KeywordToken::Fun => "fun",
+            KeywordToken::If => "if",
+            KeywordToken::Else => "else",
+            KeywordToken::Until => "until",
+            KeywordToken::Loop => "loop",
+            KeywordToken::Return => "return",
+            KeywordToken::Const => "const",
+            KeywordToken::Import => "import",
+        }
+    }
+
     pub fn length(&self) -> usize {
         match self {
             KeywordToken::Var => 3,
@@ -32,11 +51,13 @@ impl KeywordToken {
             KeywordToken::Until => 5,
             KeywordToken::Loop => 4,
             KeywordToken::Return => 6,
+            KeywordToken::Const => 5,
+            KeywordToken::Import => 6,
         }
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum SyntaxToken {
     LBrace,
     RBrace,
@@ -101,7 +122,36 @@ impl SyntaxToken {
         }
         token
     }
-    
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyntaxToken::LBrace => "{",
+            SyntaxToken::RBrace => "}",
+            SyntaxToken::LParen => "(",
+            SyntaxToken::RParen => ")",
+            SyntaxToken::Assign => ":",
+            SyntaxToken::Comma => ",",
+            SyntaxToken::Dot => ".",
+            SyntaxToken::Minus => "-",
+            SyntaxToken::Not => "!",
+            SyntaxToken::Plus => "+",
+            SyntaxToken::Times => "*",
+            SyntaxToken::Slash => "/",
+            SyntaxToken::Mod => "%",
+            SyntaxToken::And => "&",
+            SyntaxToken::Or => "|",
+            SyntaxToken::Xor => "^",
+            SyntaxToken::Eq => "==",
+            SyntaxToken::Neq => "!=",
+            SyntaxToken::Lt => "<",
+            SyntaxToken::Leq => "<=",
+            SyntaxToken::Gt => ">",
+            SyntaxToken::Geq => ">=",
+            SyntaxToken::LShift => "<<",
+            SyntaxToken::RShift => ">>",
+        }
+    }
+
     pub fn length(&self) -> usize {
         match self {
             SyntaxToken::LBrace => 1,
@@ -132,9 +182,13 @@ impl SyntaxToken {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub enum LiteralToken {
-    Character(char),
-    Integer(i64),
-    String(String),
+    /// `value` has its escape sequence decoded, `raw` is the literal as written, quotes included.
+    Character { value: char, raw: String },
+    /// `value` may be `i64::MAX + 1`, which is only valid as the operand of a unary minus.
+    /// `raw` is the literal as written, including any radix prefix and `_` separators.
+    Integer { value: u64, raw: String },
+    /// `value` has its escape sequences decoded, `raw` is the literal as written, quotes included.
+    String { value: String, raw: String },
 }
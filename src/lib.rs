@@ -1,6 +1,9 @@
-mod ast;
+pub mod ast;
+pub mod diagnostics;
 pub mod lex;
-mod parser;
+pub mod parser;
+pub mod source;
+pub mod symbol;
 
 extern crate pretty_env_logger;
 #[macro_use]
@@ -0,0 +1,30 @@
+use desolation::diagnostics::{explain, EXPLANATIONS};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: desolation explain [CODE]";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["explain"] => {
+            for explanation in EXPLANATIONS {
+                println!("{}  {}", explanation.code, explanation.title);
+            }
+            ExitCode::SUCCESS
+        }
+        ["explain", code] => match explain(code) {
+            Some(explanation) => {
+                print!("{}", explanation);
+                ExitCode::SUCCESS
+            }
+            None => {
+                eprintln!("error: no extended information for {}", code);
+                ExitCode::FAILURE
+            }
+        },
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::FAILURE
+        }
+    }
+}
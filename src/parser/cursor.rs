@@ -0,0 +1,289 @@
+use crate::lex::lexer::TokenStream;
+use crate::lex::token::{Token, TokenKind, TokenType};
+use crate::parser::ParseError;
+use crate::source::Span;
+
+/// Reads a `TokenStream` for the parser.
+///
+/// NL tokens end statements, so they are significant by default; inside
+/// `with_newlines_ignored` they are skipped over as if they were whitespace.
+/// Doc comments are only read by `doc_comments` and skipped everywhere else.
+/// The cursor always ends at an `Eof` token, which it never moves past.
+#[derive(Debug, Clone)]
+pub struct TokenCursor {
+    tokens: Vec<Token>,
+    pos: usize,
+    // The index of the token bumped last.
+    prev: Option<usize>,
+    newlines_significant: bool,
+}
+
+/// A saved cursor position to `rewind` to when backtracking.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Checkpoint {
+    pos: usize,
+    prev: Option<usize>,
+    newlines_significant: bool,
+}
+
+impl TokenCursor {
+    pub fn new(stream: TokenStream) -> Self {
+        let mut tokens = stream.tokens;
+        if tokens.last().map(Token::kind) != Some(TokenKind::Eof) {
+            let end = tokens.last().map_or(Span::default(), |t| {
+                let span = t.span();
+                Span::new(span.file, span.end, span.end)
+            });
+            let (line_no, col_no) = tokens
+                .last()
+                .map_or((1, 1), |t| (t.line_no(), t.col_no() + t.length()));
+            tokens.push(TokenType::Eof.at(end, line_no, col_no));
+        }
+        TokenCursor {
+            tokens,
+            pos: 0,
+            prev: None,
+            newlines_significant: true,
+        }
+    }
+
+    // Index of the first significant token at or after `pos`.
+    fn skip_insignificant(&self, mut pos: usize) -> usize {
+        while pos + 1 < self.tokens.len() {
+            match self.tokens[pos].kind() {
+                TokenKind::DocComment => {}
+                TokenKind::NL if !self.newlines_significant => {}
+                _ => break,
+            }
+            pos += 1;
+        }
+        pos
+    }
+
+    /// The next token.
+    pub fn peek(&self) -> &Token {
+        &self.tokens[self.skip_insignificant(self.pos)]
+    }
+
+    /// The token `n` tokens after the next one, or `Eof` if there aren't that many.
+    pub fn peek_nth(&self, n: usize) -> &Token {
+        let mut pos = self.skip_insignificant(self.pos);
+        for _ in 0..n {
+            pos = self.skip_insignificant((pos + 1).min(self.tokens.len() - 1));
+        }
+        &self.tokens[pos]
+    }
+
+    pub fn at(&self, kind: TokenKind) -> bool {
+        self.peek().kind() == kind
+    }
+
+    pub fn at_eof(&self) -> bool {
+        self.at(TokenKind::Eof)
+    }
+
+    /// Moves past the next token and returns it. At the end, keeps returning `Eof`.
+    pub fn bump(&mut self) -> Token {
+        let pos = self.skip_insignificant(self.pos);
+        self.pos = (pos + 1).min(self.tokens.len() - 1);
+        self.prev = Some(pos);
+        self.tokens[pos].clone()
+    }
+
+    /// The span of the token bumped last, or an empty span before the first token.
+    pub fn prev_span(&self) -> Span {
+        match self.prev {
+            Some(pos) => self.tokens[pos].span(),
+            None => {
+                let first = self.tokens[0].span();
+                Span::new(first.file, first.start, first.start)
+            }
+        }
+    }
+
+    /// Bumps the next token if it is of the given kind.
+    pub fn eat(&mut self, kind: TokenKind) -> Option<Token> {
+        self.at(kind).then(|| self.bump())
+    }
+
+    /// Bumps the next token, failing if it is not of the given kind.
+    pub fn expect(&mut self, kind: TokenKind) -> Result<Token, ParseError> {
+        self.eat(kind)
+            .ok_or_else(|| ParseError::unexpected(kind, self.peek()))
+    }
+
+    /// Skips any NL tokens, even where they are significant.
+    pub fn skip_newlines(&mut self) {
+        while self.tokens[self.pos].kind() == TokenKind::NL {
+            self.pos += 1;
+        }
+    }
+
+    /// Moves past the doc comments directly ahead, with the NLs ending them,
+    /// and returns their text. The parser attaches it to the `fun` or `var`
+    /// that follows; doc comments anywhere else are dropped.
+    pub fn doc_comments(&mut self) -> Vec<String> {
+        let mut docs = Vec::new();
+        while let TokenType::DocComment { text, .. } = self.tokens[self.pos].token_type() {
+            docs.push(text.clone());
+            self.pos += 1;
+            self.skip_newlines();
+        }
+        docs
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            pos: self.pos,
+            prev: self.prev,
+            newlines_significant: self.newlines_significant,
+        }
+    }
+
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.pos = checkpoint.pos;
+        self.prev = checkpoint.prev;
+        self.newlines_significant = checkpoint.newlines_significant;
+    }
+
+    /// Sets whether NL tokens are significant, returning the previous setting.
+    pub fn set_newlines_significant(&mut self, significant: bool) -> bool {
+        std::mem::replace(&mut self.newlines_significant, significant)
+    }
+
+    /// Runs `f` on `owner`, the cursor or a parser holding it, with NL tokens
+    /// skipped, e.g. inside parentheses.
+    pub fn with_newlines_ignored<P, T>(owner: &mut P, f: impl FnOnce(&mut P) -> T) -> T
+    where
+        P: AsMut<TokenCursor>,
+    {
+        let significant = owner.as_mut().set_newlines_significant(false);
+        let result = f(owner);
+        owner.as_mut().set_newlines_significant(significant);
+        result
+    }
+}
+
+impl AsMut<TokenCursor> for TokenCursor {
+    fn as_mut(&mut self) -> &mut TokenCursor {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::{Diagnostic, Renderer};
+    use crate::lex::types::{KeywordToken, SyntaxToken};
+    use crate::lex::Lexer;
+    use crate::source::SourceMap;
+
+    fn cursor(source: &str) -> TokenCursor {
+        TokenCursor::new(Lexer::new().lex_str(source).unwrap())
+    }
+
+    #[test]
+    fn peeks_bumps_and_stops_at_eof() {
+        let mut tokens = cursor("f(a)");
+        assert_eq!(tokens.peek().kind(), TokenKind::Identifier);
+        assert_eq!(tokens.peek_nth(2).kind(), TokenKind::Identifier);
+        assert_eq!(tokens.peek_nth(10).kind(), TokenKind::Eof);
+        assert!(tokens.eat(TokenKind::Syntax(SyntaxToken::LParen)).is_none());
+        tokens.bump();
+        assert!(tokens.eat(TokenKind::Syntax(SyntaxToken::LParen)).is_some());
+        let err = tokens
+            .expect(TokenKind::Syntax(SyntaxToken::RParen))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Expected ')', found identifier at 1:3");
+        tokens.bump();
+        tokens.bump();
+        assert!(tokens.at_eof());
+        assert_eq!(tokens.bump().kind(), TokenKind::Eof);
+        assert!(tokens.at_eof());
+    }
+
+    #[test]
+    fn skips_newlines_only_where_insignificant() {
+        let mut tokens = cursor("(a\n,\nb)\nc");
+        tokens.bump();
+        TokenCursor::with_newlines_ignored(&mut tokens, |tokens| {
+            let kinds = (0..4)
+                .map(|n| tokens.peek_nth(n).kind())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                kinds,
+                vec![
+                    TokenKind::Identifier,
+                    TokenKind::Syntax(SyntaxToken::Comma),
+                    TokenKind::Identifier,
+                    TokenKind::Syntax(SyntaxToken::RParen),
+                ]
+            );
+            tokens.bump();
+            tokens
+                .expect(TokenKind::Syntax(SyntaxToken::Comma))
+                .unwrap();
+            tokens.bump();
+            tokens
+                .expect(TokenKind::Syntax(SyntaxToken::RParen))
+                .unwrap();
+        });
+        assert!(tokens.at(TokenKind::NL));
+    }
+
+    #[test]
+    fn rewinds_to_checkpoints() {
+        let mut tokens = cursor("a b\nc");
+        let start = tokens.checkpoint();
+        tokens.bump();
+        tokens.bump();
+        assert_eq!(tokens.peek().kind(), TokenKind::NL);
+        tokens.rewind(start);
+        assert_eq!(tokens.bump().text(), "a");
+    }
+
+    #[test]
+    fn collects_doc_comments() {
+        let mut tokens = cursor("## Squares n.\n##\n##   Really.\nfun sq(n)");
+        assert_eq!(tokens.doc_comments(), vec!["Squares n.", "", "  Really."]);
+        assert_eq!(tokens.peek().kind(), TokenKind::Keyword(KeywordToken::Fun));
+        assert!(tokens.doc_comments().is_empty());
+    }
+
+    #[test]
+    fn skips_doc_comments_elsewhere() {
+        let mut tokens = cursor("x : 1 ## The answer.\ng(1,\n ## Second.\n 2)");
+        let texts = (0..5).map(|_| tokens.bump().text().into_owned());
+        assert_eq!(texts.collect::<Vec<_>>(), vec!["x", ":", "1", "\n", "g"]);
+        let texts = TokenCursor::with_newlines_ignored(&mut tokens, |tokens| {
+            (0..5)
+                .map(|_| tokens.bump().text().into_owned())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(texts, vec!["(", "1", ",", "2", ")"]);
+    }
+
+    #[test]
+    fn renders_expect_errors() {
+        let mut map = SourceMap::new();
+        let file = map.add_file("test.t", "f(a b)\n");
+        let mut tokens = TokenCursor::new(Lexer::for_file(file).lex_str("f(a b)\n").unwrap());
+        tokens.bump();
+        tokens.bump();
+        tokens.bump();
+        let err = tokens
+            .expect(TokenKind::Syntax(SyntaxToken::Comma))
+            .unwrap_err();
+        assert_eq!(
+            Renderer::new().render(&err.report(), map.get(file).unwrap()),
+            "error[P0001]: expected ',', found identifier
+ --> test.t:1:5
+  |
+1 | f(a b)
+  |     ^ expected ','
+  |
+  = help: add the missing token: `,`
+"
+        );
+    }
+}
@@ -0,0 +1,663 @@
+use super::parens::binary_op;
+use super::precedence::{self, Associativity, Fixity, Precedence};
+use super::{ParseError, Parser, TokenCursor};
+use crate::ast::{Expr, ExprKind, Fun, Item, NodeId, Program, Stmt, StmtKind, UnaryOp, Var, ID};
+
+use crate::lex::token::{TokenKind, TokenType};
+use crate::lex::types::{KeywordToken, LiteralToken, SyntaxToken};
+use crate::source::Span;
+
+const ASSIGN: TokenKind = TokenKind::Syntax(SyntaxToken::Assign);
+const COMMA: TokenKind = TokenKind::Syntax(SyntaxToken::Comma);
+const LBRACE: TokenKind = TokenKind::Syntax(SyntaxToken::LBrace);
+const RBRACE: TokenKind = TokenKind::Syntax(SyntaxToken::RBrace);
+const LPAREN: TokenKind = TokenKind::Syntax(SyntaxToken::LParen);
+const RPAREN: TokenKind = TokenKind::Syntax(SyntaxToken::RParen);
+
+// The magnitude of the smallest integer, which is only written negated.
+const MIN_INTEGER: u64 = i64::MAX as u64 + 1;
+
+/// A recursive-descent parser, one method per construct.
+impl Parser {
+    pub(super) fn program(&mut self) -> Result<Program, ParseError> {
+        let mut items = Vec::new();
+        self.tokens.skip_newlines();
+        while !self.tokens.at_eof() {
+            let docs = self.tokens.doc_comments();
+            // Doc comments with nothing after them document nothing.
+            if self.tokens.at_eof() {
+                break;
+            }
+            let item = match self.tokens.peek().kind() {
+                TokenKind::Keyword(KeywordToken::Var) => Item::Var(self.var(docs)?),
+                TokenKind::Keyword(KeywordToken::Fun) => Item::Fun(self.fun(docs)?),
+                _ => return Err(ParseError::expected("a declaration", self.tokens.peek())),
+            };
+            items.push(item);
+            self.end_of_statement()?;
+        }
+        Ok(Program { items })
+    }
+
+    // Gives the node that started at `start` and ends with the token bumped last its id and span.
+    fn finish(&mut self, start: Span) -> (NodeId, Span) {
+        (self.ids.next_id(), start.to(self.tokens.prev_span()))
+    }
+
+    fn expr_node(&mut self, start: Span, kind: ExprKind) -> Expr {
+        let (id, span) = self.finish(start);
+        Expr::new(id, span, kind)
+    }
+
+    fn stmt_node(&mut self, start: Span, kind: StmtKind) -> Stmt {
+        let (id, span) = self.finish(start);
+        Stmt::new(id, span, kind)
+    }
+
+    fn var(&mut self, docs: Vec<String>) -> Result<Var, ParseError> {
+        let start = self
+            .tokens
+            .expect(TokenKind::Keyword(KeywordToken::Var))?
+            .span();
+        let (name, _) = self.identifier()?;
+        let value = match self.tokens.eat(ASSIGN) {
+            Some(_) => Some(self.expr()?),
+            None => None,
+        };
+        let (id, span) = self.finish(start);
+        Ok(Var {
+            id,
+            span,
+            docs,
+            name,
+            value,
+        })
+    }
+
+    fn fun(&mut self, docs: Vec<String>) -> Result<Fun, ParseError> {
+        let start = self
+            .tokens
+            .expect(TokenKind::Keyword(KeywordToken::Fun))?
+            .span();
+        let (name, _) = self.identifier()?;
+        self.tokens.expect(LPAREN)?;
+        let params = TokenCursor::with_newlines_ignored(self, |p| {
+            p.comma_separated(RPAREN, |p| {
+                let (name, span) = p.identifier()?;
+                Ok(ID {
+                    id: p.ids.next_id(),
+                    span,
+                    name,
+                })
+            })
+        })?;
+        let body = self.block()?;
+        let (id, span) = self.finish(start);
+        Ok(Fun {
+            id,
+            span,
+            docs,
+            name,
+            params,
+            body,
+        })
+    }
+
+    fn identifier(&mut self) -> Result<(String, Span), ParseError> {
+        let token = self.tokens.expect(TokenKind::Identifier)?;
+        match token.token_type() {
+            // The symbol, not `text()`, which keeps the spelling before NFC normalization.
+            TokenType::IdentifierToken(name) => Ok((name.as_str().to_owned(), token.span())),
+            _ => unreachable!("identifier token without a symbol"),
+        }
+    }
+
+    // Parses `item`s separated by commas, up to and including `close`.
+    fn comma_separated<T>(
+        &mut self,
+        close: TokenKind,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut items = Vec::new();
+        if self.tokens.eat(close).is_some() {
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            if self.tokens.eat(close).is_some() {
+                return Ok(items);
+            }
+            self.tokens.expect(COMMA)?;
+        }
+    }
+
+    fn at_end_of_statement(&self) -> bool {
+        self.tokens.at(TokenKind::NL) || self.tokens.at(RBRACE) || self.tokens.at_eof()
+    }
+
+    // A statement ends at a newline, or at the `}` or end of file after the last one.
+    fn end_of_statement(&mut self) -> Result<(), ParseError> {
+        if !self.tokens.at(RBRACE) && !self.tokens.at_eof() {
+            self.tokens.expect(TokenKind::NL)?;
+        }
+        self.tokens.skip_newlines();
+        Ok(())
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        self.tokens.expect(LBRACE)?;
+        self.tokens.skip_newlines();
+        let mut body = Vec::new();
+        while self.tokens.eat(RBRACE).is_none() {
+            if self.tokens.at_eof() {
+                return Err(ParseError::unexpected(RBRACE, self.tokens.peek()));
+            }
+            let docs = self.tokens.doc_comments();
+            if self.tokens.at(RBRACE) {
+                continue;
+            }
+            body.push(self.statement(docs)?);
+            self.end_of_statement()?;
+        }
+        Ok(body)
+    }
+
+    fn statement(&mut self, docs: Vec<String>) -> Result<Stmt, ParseError> {
+        let start = self.tokens.peek().span();
+        let kind = match self.tokens.peek().kind() {
+            TokenKind::Keyword(KeywordToken::Var) => StmtKind::Var(self.var(docs)?),
+            TokenKind::Keyword(KeywordToken::If) => return self.if_statement(),
+            TokenKind::Keyword(KeywordToken::Loop) => {
+                self.tokens.bump();
+                StmtKind::Loop(self.block()?)
+            }
+            TokenKind::Keyword(KeywordToken::Until) => {
+                self.tokens.bump();
+                StmtKind::Until(self.expr()?)
+            }
+            TokenKind::Keyword(KeywordToken::Return) => {
+                self.tokens.bump();
+                if self.at_end_of_statement() {
+                    StmtKind::Return(None)
+                } else {
+                    StmtKind::Return(Some(self.expr()?))
+                }
+            }
+            _ => {
+                let expr = self.expr()?;
+                match self.tokens.eat(ASSIGN) {
+                    Some(_) => StmtKind::Assign {
+                        target: expr,
+                        value: self.expr()?,
+                    },
+                    None => StmtKind::Expr(expr),
+                }
+            }
+        };
+        Ok(self.stmt_node(start, kind))
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        let start = self
+            .tokens
+            .expect(TokenKind::Keyword(KeywordToken::If))?
+            .span();
+        let cond = self.expr()?;
+        let then_body = self.block()?;
+        // `else` may start a later line than the `}`, after doc comments even.
+        let before_else = self.tokens.checkpoint();
+        self.tokens.skip_newlines();
+        self.tokens.doc_comments();
+        let else_body = if self
+            .tokens
+            .eat(TokenKind::Keyword(KeywordToken::Else))
+            .is_some()
+        {
+            if self.tokens.at(TokenKind::Keyword(KeywordToken::If)) {
+                Some(vec![self.if_statement()?])
+            } else {
+                Some(self.block()?)
+            }
+        } else {
+            self.tokens.rewind(before_else);
+            None
+        };
+        let kind = StmtKind::If {
+            cond,
+            then_body,
+            else_body,
+        };
+        Ok(self.stmt_node(start, kind))
+    }
+
+    pub(super) fn expr(&mut self) -> Result<Expr, ParseError> {
+        self.pratt(Precedence::Lowest)
+    }
+
+    // Parses an expression whose operators all bind tighter than `min`, going by
+    // the `OPERATORS` table.
+    fn pratt(&mut self, min: Precedence) -> Result<Expr, ParseError> {
+        let mut lhs = self.prefix()?;
+        while let TokenKind::Syntax(token) = self.tokens.peek().kind() {
+            let Some(operator) = precedence::infix(token).filter(|op| op.precedence > min) else {
+                break;
+            };
+            self.tokens.bump();
+            let start = lhs.span;
+            let kind = match operator.fixity {
+                Fixity::Infix(associativity) => {
+                    let min = match associativity {
+                        Associativity::Left => operator.precedence,
+                        Associativity::Right => operator.precedence.looser(),
+                    };
+                    let op = binary_op(token).unwrap();
+                    ExprKind::Binary(op, Box::new(lhs), Box::new(self.pratt(min)?))
+                }
+                Fixity::Postfix => {
+                    let args = TokenCursor::with_newlines_ignored(self, |p| {
+                        p.comma_separated(RPAREN, Self::expr)
+                    })?;
+                    ExprKind::Call(Box::new(lhs), args)
+                }
+                Fixity::Prefix => unreachable!("prefix operator {:?} in infix position", token),
+            };
+            lhs = self.expr_node(start, kind);
+        }
+        Ok(lhs)
+    }
+
+    fn prefix(&mut self) -> Result<Expr, ParseError> {
+        let operator = match self.tokens.peek().kind() {
+            TokenKind::Syntax(token) => precedence::prefix(token),
+            _ => None,
+        };
+        let Some(operator) = operator else {
+            return self.primary();
+        };
+        let start = self.tokens.bump().span();
+        // `-9223372036854775808` is in range, but only if the literal is the
+        // whole operand: in `-9223372036854775808(1)` it is called before it is
+        // negated, and overflows like any other callee.
+        if let (SyntaxToken::Minus, TokenType::Literal(LiteralToken::Integer { value, .. })) =
+            (operator.token, self.tokens.peek().token_type())
+        {
+            if *value == MIN_INTEGER && !self.at_postfix(1) {
+                let literal_start = self.tokens.bump().span();
+                let literal = self.expr_node(literal_start, ExprKind::Integer(MIN_INTEGER));
+                return Ok(self.expr_node(start, ExprKind::Unary(UnaryOp::Neg, Box::new(literal))));
+            }
+        }
+        // Prefix operators are right-associative, so `--x` is `-(-x)`.
+        let operand = Box::new(self.pratt(operator.precedence.looser())?);
+        let kind = match operator.token {
+            SyntaxToken::Minus => ExprKind::Unary(UnaryOp::Neg, operand),
+            SyntaxToken::Not => ExprKind::Unary(UnaryOp::Not, operand),
+            SyntaxToken::Dot => ExprKind::Deref(operand),
+            token => unreachable!("unknown prefix operator {:?}", token),
+        };
+        Ok(self.expr_node(start, kind))
+    }
+
+    // Whether the token `n` tokens ahead continues an operand, like the `(` of a call.
+    fn at_postfix(&self, n: usize) -> bool {
+        match self.tokens.peek_nth(n).kind() {
+            TokenKind::Syntax(token) => {
+                precedence::infix(token).is_some_and(|op| op.fixity == Fixity::Postfix)
+            }
+            _ => false,
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let token = self.tokens.bump();
+        let kind = match token.token_type() {
+            TokenType::Literal(LiteralToken::Integer { value, .. }) => {
+                if *value >= MIN_INTEGER {
+                    return Err(ParseError::IntegerOverflow {
+                        found: Box::new(token),
+                    });
+                }
+                ExprKind::Integer(*value)
+            }
+            TokenType::Literal(LiteralToken::String { value, .. }) => {
+                ExprKind::String(value.clone())
+            }
+            TokenType::Literal(LiteralToken::Character { value, .. }) => {
+                ExprKind::Character(*value)
+            }
+            TokenType::IdentifierToken(name) => ExprKind::Var(name.as_str().to_owned()),
+            TokenType::Syntax(SyntaxToken::LParen) => {
+                let inner = TokenCursor::with_newlines_ignored(self, |p| {
+                    let inner = p.expr()?;
+                    p.tokens.expect(RPAREN)?;
+                    Ok(inner)
+                })?;
+                ExprKind::Group(Box::new(inner))
+            }
+            _ => return Err(ParseError::expected("an expression", &token)),
+        };
+        Ok(self.expr_node(token.span(), kind))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Diagnostic;
+    use crate::lex::Lexer;
+    use crate::parser::parens::binary_token;
+
+    fn parse(source: &str) -> Result<Program, ParseError> {
+        Parser::new(Lexer::new().lex_str(source).unwrap()).program()
+    }
+
+    fn funs(source: &str) -> Vec<Fun> {
+        parse(source).unwrap().funs().cloned().collect()
+    }
+
+    fn body(source: &str) -> String {
+        let body = funs(&format!("fun f() {{\n{}\n}}", source)).remove(0).body;
+        stmts(&body)
+    }
+
+    fn expr(source: &str) -> String {
+        let mut body = funs(&format!("fun f() {{\n{}\n}}", source)).remove(0).body;
+        match body.remove(0).kind {
+            StmtKind::Expr(e) => sexp(&e),
+            stmt => panic!("expected an expression, got {:?}", stmt),
+        }
+    }
+
+    fn error(source: &str) -> String {
+        parse(source).unwrap_err().to_string()
+    }
+
+    // Prints the tree as an s-expression, leaving out ids and spans.
+    fn sexp(expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Integer(value) => value.to_string(),
+            ExprKind::String(value) => format!("{:?}", value),
+            ExprKind::Character(value) => format!("{:?}", value),
+            ExprKind::Var(name) => name.clone(),
+            ExprKind::Deref(e) => format!("(. {})", sexp(e)),
+            ExprKind::Unary(UnaryOp::Neg, e) => format!("(- {})", sexp(e)),
+            ExprKind::Unary(UnaryOp::Not, e) => format!("(! {})", sexp(e)),
+            ExprKind::Binary(op, lhs, rhs) => format!(
+                "({} {} {})",
+                binary_token(*op).as_str(),
+                sexp(lhs),
+                sexp(rhs)
+            ),
+            ExprKind::Call(callee, args) => {
+                let mut parts = vec![sexp(callee)];
+                parts.extend(args.iter().map(sexp));
+                format!("(call {})", parts.join(" "))
+            }
+            ExprKind::Group(e) => format!("(group {})", sexp(e)),
+        }
+    }
+
+    fn stmt(stmt: &Stmt) -> String {
+        match &stmt.kind {
+            StmtKind::Var(var) => var_sexp(var),
+            StmtKind::Assign { target, value } => format!("(: {} {})", sexp(target), sexp(value)),
+            StmtKind::Expr(e) => sexp(e),
+            StmtKind::If {
+                cond,
+                then_body,
+                else_body: None,
+            } => format!("(if {} {})", sexp(cond), stmts(then_body)),
+            StmtKind::If {
+                cond,
+                then_body,
+                else_body: Some(else_body),
+            } => format!(
+                "(if {} {} {})",
+                sexp(cond),
+                stmts(then_body),
+                stmts(else_body)
+            ),
+            StmtKind::Loop(body) => format!("(loop {})", stmts(body)),
+            StmtKind::Until(cond) => format!("(until {})", sexp(cond)),
+            StmtKind::Return(None) => "(return)".to_string(),
+            StmtKind::Return(Some(value)) => format!("(return {})", sexp(value)),
+        }
+    }
+
+    fn stmts(body: &[Stmt]) -> String {
+        format!(
+            "{{{}}}",
+            body.iter().map(stmt).collect::<Vec<_>>().join(" ")
+        )
+    }
+
+    fn var_sexp(var: &Var) -> String {
+        match &var.value {
+            Some(value) => format!("(var {} {})", var.name, sexp(value)),
+            None => format!("(var {})", var.name),
+        }
+    }
+
+    fn fun_sexp(fun: &Fun) -> String {
+        let params = fun.params.iter().map(|p| p.name.as_str());
+        format!(
+            "(fun {} ({}) {})",
+            fun.name,
+            params.collect::<Vec<_>>().join(" "),
+            stmts(&fun.body)
+        )
+    }
+
+    #[test]
+    fn parses_the_squares_example() {
+        let source = std::fs::read_to_string("examples/sq.t").unwrap();
+        let funs = funs(&source);
+        assert_eq!(
+            funs.iter().map(fun_sexp).collect::<Vec<_>>(),
+            vec![
+                "(fun sq (n) {(return (* (. n) (. n)))})",
+                "(fun init () {(var i) (call sprint \"Table of squares:\\n\") (: i 1) \
+                 (loop {(until (>= (. i) 10)) (call iprint (. i)) \
+                 (call sprint \" squared equals \") (call iprint (call sq (. i))) (call nl) \
+                 (: i (+ (. i) 1))})})",
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_global_vars_with_docs() {
+        let program = parse("## The answer.\nvar x : 42\n\nvar y\n").unwrap();
+        let vars = program.vars().collect::<Vec<_>>();
+        assert_eq!(
+            vars.iter().map(|var| var_sexp(var)).collect::<Vec<_>>(),
+            vec!["(var x 42)", "(var y)"]
+        );
+        assert_eq!(vars[0].docs, vec!["The answer."]);
+        assert!(vars[1].docs.is_empty());
+        assert_eq!(parse("").unwrap(), Program::default());
+    }
+
+    #[test]
+    fn keeps_vars_and_funs_in_source_order() {
+        let program = parse("fun f() {\n    g(.n)\n}\nvar n : 1\n\nfun g(x) {}\n").unwrap();
+        let items = program
+            .items
+            .iter()
+            .map(|item| match item {
+                Item::Var(var) => var_sexp(var),
+                Item::Fun(fun) => fun_sexp(fun),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            vec!["(fun f () {(call g (. n))})", "(var n 1)", "(fun g (x) {})"]
+        );
+        // `f` refers to `g` and `n`, which are declared below it.
+        assert!(matches!(program.item("g"), Some(Item::Fun(_))));
+        assert!(matches!(program.item("n"), Some(Item::Var(_))));
+        assert!(program.item("x").is_none());
+        assert_eq!(program.items[1].name(), "n");
+        assert_eq!(
+            program.items[1].span(),
+            Span::new(Default::default(), 22, 31)
+        );
+    }
+
+    #[test]
+    fn parses_params_and_args_across_lines() {
+        let funs = funs("## Adds.\nfun add(a,\n        b) {\n    return add(.a,\n  .b)\n}");
+        assert_eq!(funs[0].docs, vec!["Adds."]);
+        assert_eq!(
+            fun_sexp(&funs[0]),
+            "(fun add (a b) {(return (call add (. a) (. b)))})"
+        );
+    }
+
+    #[test]
+    fn parses_statements() {
+        assert_eq!(
+            body("## Local.\nvar x : 'a'\nreturn\n.p : x\nf()(1)"),
+            "{(var x 'a') (return) (: (. p) x) (call (call f) 1)}"
+        );
+        assert_eq!(body("loop { return }"), "{(loop {(return)})}");
+        let body = funs("fun f() {\n    ## Local.\n    var x\n}")
+            .remove(0)
+            .body;
+        match &body[0].kind {
+            StmtKind::Var(var) => assert_eq!(var.docs, vec!["Local."]),
+            stmt => panic!("expected a variable, got {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn skips_doc_comments_after_code() {
+        let program = parse("var x : 1 ## The answer.\nvar y\n").unwrap();
+        assert_eq!(
+            program.vars().map(var_sexp).collect::<Vec<_>>(),
+            vec!["(var x 1)", "(var y)"]
+        );
+        assert!(program.vars().all(|var| var.docs.is_empty()));
+        assert_eq!(body("g(1,\n ## Second.\n 2)"), "{(call g 1 2)}");
+    }
+
+    #[test]
+    fn drops_doc_comments_before_nothing() {
+        assert_eq!(body("x()\n## TODO"), "{(call x)}");
+        assert_eq!(body("## TODO"), "{}");
+        let program = parse("var x\n## End of file.\n").unwrap();
+        assert_eq!(
+            program.vars().map(var_sexp).collect::<Vec<_>>(),
+            vec!["(var x)"]
+        );
+        assert_eq!(parse("## Nothing.").unwrap(), Program::default());
+    }
+
+    #[test]
+    fn parses_if_and_else() {
+        assert_eq!(
+            body(
+                "if .a == 0 {\n    f()\n} else if .b == 0 {\n} else { g() }\nif .c == 0 {}\n\nh()"
+            ),
+            "{(if (== (. a) 0) {(call f)} {(if (== (. b) 0) {} {(call g)})}) \
+             (if (== (. c) 0) {}) (call h)}"
+        );
+        assert_eq!(body("if x {}\nelse {}"), "{(if x {} {})}");
+    }
+
+    #[test]
+    fn parses_operators_by_precedence() {
+        assert_eq!(
+            expr(".n * .n + 1 << 2 == x"),
+            "(== (<< (+ (* (. n) (. n)) 1) 2) x)"
+        );
+        assert_eq!(expr("a - b - c"), "(- (- a b) c)");
+        assert_eq!(expr("a | b ^ c & d"), "(| a (^ b (& c d)))");
+        assert_eq!(
+            expr("(a +\n b) % -!..c"),
+            "(% (group (+ a b)) (- (! (. (. c)))))"
+        );
+    }
+
+    #[test]
+    fn parses_prefix_and_call_operators() {
+        assert_eq!(expr("-f(x)"), "(- (call f x))");
+        assert_eq!(expr(".f(x)"), "(. (call f x))");
+        assert_eq!(expr("--x"), "(- (- x))");
+        assert_eq!(expr("-a * b"), "(* (- a) b)");
+        assert_eq!(expr("a < b == c != !d"), "(!= (== (< a b) c) (! d))");
+        assert_eq!(expr("f(1)(2)"), "(call (call f 1) 2)");
+    }
+
+    #[test]
+    fn gives_nodes_spans_and_post_order_ids() {
+        let source = "fun f(a) {\n    return .a + (1)\n}";
+        let fun = funs(source).remove(0);
+        let text = |span: Span| &source[span.range()];
+        assert_eq!(text(fun.span), source);
+        assert_eq!(text(fun.params[0].span), "a");
+        let StmtKind::Return(Some(sum)) = &fun.body[0].kind else {
+            panic!("expected a return, got {:?}", fun.body[0]);
+        };
+        assert_eq!(text(fun.body[0].span), "return .a + (1)");
+        assert_eq!(text(sum.span), ".a + (1)");
+        let ExprKind::Binary(_, lhs, rhs) = &sum.kind else {
+            panic!("expected a sum, got {:?}", sum);
+        };
+        assert_eq!(text(lhs.span), ".a");
+        assert_eq!(text(rhs.span), "(1)");
+
+        // a, then a, .a, 1, (1), the sum, the return and the function.
+        assert_eq!(fun.params[0].id, NodeId(0));
+        assert_eq!(lhs.id, NodeId(2));
+        assert_eq!(rhs.id, NodeId(4));
+        assert_eq!(sum.id, NodeId(5));
+        assert_eq!(fun.body[0].id, NodeId(6));
+        assert_eq!(fun.id, NodeId(7));
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let source = std::fs::read_to_string("examples/sq.t").unwrap();
+        let program = parse(&source).unwrap();
+        let json = serde_json::to_string(&program).unwrap();
+        assert_eq!(serde_json::from_str::<Program>(&json).unwrap(), program);
+    }
+
+    #[test]
+    fn only_negated_min_integer_fits() {
+        assert_eq!(expr("-9223372036854775808"), "(- 9223372036854775808)");
+        let err = parse("var x : 9223372036854775808").unwrap_err();
+        assert_eq!(err.code(), "P0005");
+        assert_eq!(
+            err.to_string(),
+            "Integer literal 9223372036854775808 out of range at 1:9"
+        );
+        assert_eq!(expr("-5(1)"), "(- (call 5 1))");
+        let err = parse("var x : -9223372036854775808(1)").unwrap_err();
+        assert_eq!(err.code(), "P0005");
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        assert_eq!(
+            error("x : 1"),
+            "Expected a declaration, found identifier at 1:1"
+        );
+        assert_eq!(
+            error("var x y"),
+            "Expected newline, found identifier at 1:7"
+        );
+        assert_eq!(
+            error("fun f() {\n    x :\n}"),
+            "Expected an expression, found newline at 2:8"
+        );
+        assert_eq!(
+            error("fun f(a b) {}"),
+            "Expected ',', found identifier at 1:9"
+        );
+        assert_eq!(
+            error("fun f() {\n    loop {\n"),
+            "Expected '}', found end of file at 3:1"
+        );
+    }
+}
@@ -0,0 +1,474 @@
+//! The language's grammar, for the table-driven parser. The build script
+//! includes this file along with `lalr.rs`, `precedence.rs` and the lexer's
+//! `types.rs`, so it must only use `std` and those modules.
+
+use super::lalr::{Grammar, Rule, Symbol};
+use super::precedence::{Associativity, Fixity, Precedence, OPERATORS};
+use crate::lex::types::SyntaxToken;
+use std::sync::OnceLock;
+
+/// The tokens the grammar is written in. NL tokens inside parentheses, runs
+/// of NL tokens and an NL token before `else` are dropped before parsing.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Terminal {
+    Var,
+    Fun,
+    If,
+    Else,
+    Loop,
+    Until,
+    Return,
+    Identifier,
+    Integer,
+    String,
+    Character,
+    LBrace,
+    RBrace,
+    LParen,
+    RParen,
+    Assign,
+    Comma,
+    Dot,
+    Minus,
+    Not,
+    Plus,
+    Times,
+    Slash,
+    Mod,
+    And,
+    Or,
+    Xor,
+    Eq,
+    Neq,
+    Lt,
+    Leq,
+    Gt,
+    Geq,
+    LShift,
+    RShift,
+    NL,
+    Eof,
+}
+
+impl Terminal {
+    pub const ALL: [Terminal; 37] = [
+        Terminal::Var,
+        Terminal::Fun,
+        Terminal::If,
+        Terminal::Else,
+        Terminal::Loop,
+        Terminal::Until,
+        Terminal::Return,
+        Terminal::Identifier,
+        Terminal::Integer,
+        Terminal::String,
+        Terminal::Character,
+        Terminal::LBrace,
+        Terminal::RBrace,
+        Terminal::LParen,
+        Terminal::RParen,
+        Terminal::Assign,
+        Terminal::Comma,
+        Terminal::Dot,
+        Terminal::Minus,
+        Terminal::Not,
+        Terminal::Plus,
+        Terminal::Times,
+        Terminal::Slash,
+        Terminal::Mod,
+        Terminal::And,
+        Terminal::Or,
+        Terminal::Xor,
+        Terminal::Eq,
+        Terminal::Neq,
+        Terminal::Lt,
+        Terminal::Leq,
+        Terminal::Gt,
+        Terminal::Geq,
+        Terminal::LShift,
+        Terminal::RShift,
+        Terminal::NL,
+        Terminal::Eof,
+    ];
+
+    pub fn syntax(token: SyntaxToken) -> Terminal {
+        match token {
+            SyntaxToken::LBrace => Terminal::LBrace,
+            SyntaxToken::RBrace => Terminal::RBrace,
+            SyntaxToken::LParen => Terminal::LParen,
+            SyntaxToken::RParen => Terminal::RParen,
+            SyntaxToken::Assign => Terminal::Assign,
+            SyntaxToken::Comma => Terminal::Comma,
+            SyntaxToken::Dot => Terminal::Dot,
+            SyntaxToken::Minus => Terminal::Minus,
+            SyntaxToken::Not => Terminal::Not,
+            SyntaxToken::Plus => Terminal::Plus,
+            SyntaxToken::Times => Terminal::Times,
+            SyntaxToken::Slash => Terminal::Slash,
+            SyntaxToken::Mod => Terminal::Mod,
+            SyntaxToken::And => Terminal::And,
+            SyntaxToken::Or => Terminal::Or,
+            SyntaxToken::Xor => Terminal::Xor,
+            SyntaxToken::Eq => Terminal::Eq,
+            SyntaxToken::Neq => Terminal::Neq,
+            SyntaxToken::Lt => Terminal::Lt,
+            SyntaxToken::Leq => Terminal::Leq,
+            SyntaxToken::Gt => Terminal::Gt,
+            SyntaxToken::Geq => Terminal::Geq,
+            SyntaxToken::LShift => Terminal::LShift,
+            SyntaxToken::RShift => Terminal::RShift,
+        }
+    }
+
+    /// Spelled the way `TokenKind` displays.
+    pub fn name(self) -> &'static str {
+        match self {
+            Terminal::Var => "'var'",
+            Terminal::Fun => "'fun'",
+            Terminal::If => "'if'",
+            Terminal::Else => "'else'",
+            Terminal::Loop => "'loop'",
+            Terminal::Until => "'until'",
+            Terminal::Return => "'return'",
+            Terminal::Identifier => "identifier",
+            Terminal::Integer => "integer literal",
+            Terminal::String => "string literal",
+            Terminal::Character => "character literal",
+            Terminal::LBrace => "'{'",
+            Terminal::RBrace => "'}'",
+            Terminal::LParen => "'('",
+            Terminal::RParen => "')'",
+            Terminal::Assign => "':'",
+            Terminal::Comma => "','",
+            Terminal::Dot => "'.'",
+            Terminal::Minus => "'-'",
+            Terminal::Not => "'!'",
+            Terminal::Plus => "'+'",
+            Terminal::Times => "'*'",
+            Terminal::Slash => "'/'",
+            Terminal::Mod => "'%'",
+            Terminal::And => "'&'",
+            Terminal::Or => "'|'",
+            Terminal::Xor => "'^'",
+            Terminal::Eq => "'=='",
+            Terminal::Neq => "'!='",
+            Terminal::Lt => "'<'",
+            Terminal::Leq => "'<='",
+            Terminal::Gt => "'>'",
+            Terminal::Geq => "'>='",
+            Terminal::LShift => "'<<'",
+            Terminal::RShift => "'>>'",
+            Terminal::NL => "newline",
+            Terminal::Eof => "end of file",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum NonTerminal {
+    Program,
+    OptNl,
+    ItemList,
+    Item,
+    VarDecl,
+    FunDecl,
+    Params,
+    Block,
+    StmtList,
+    Stmt,
+    IfStmt,
+    Expr,
+    OrExpr,
+    XorExpr,
+    AndExpr,
+    EqExpr,
+    CmpExpr,
+    ShiftExpr,
+    AddExpr,
+    MulExpr,
+    UnaryExpr,
+    PostfixExpr,
+    Args,
+    Primary,
+}
+
+impl NonTerminal {
+    pub const ALL: [NonTerminal; 24] = [
+        NonTerminal::Program,
+        NonTerminal::OptNl,
+        NonTerminal::ItemList,
+        NonTerminal::Item,
+        NonTerminal::VarDecl,
+        NonTerminal::FunDecl,
+        NonTerminal::Params,
+        NonTerminal::Block,
+        NonTerminal::StmtList,
+        NonTerminal::Stmt,
+        NonTerminal::IfStmt,
+        NonTerminal::Expr,
+        NonTerminal::OrExpr,
+        NonTerminal::XorExpr,
+        NonTerminal::AndExpr,
+        NonTerminal::EqExpr,
+        NonTerminal::CmpExpr,
+        NonTerminal::ShiftExpr,
+        NonTerminal::AddExpr,
+        NonTerminal::MulExpr,
+        NonTerminal::UnaryExpr,
+        NonTerminal::PostfixExpr,
+        NonTerminal::Args,
+        NonTerminal::Primary,
+    ];
+
+    /// The nonterminal for the expressions whose outermost operator binds at
+    /// `precedence`, or tighter.
+    pub fn level(precedence: Precedence) -> NonTerminal {
+        match precedence {
+            Precedence::Lowest => NonTerminal::Expr,
+            Precedence::Or => NonTerminal::OrExpr,
+            Precedence::Xor => NonTerminal::XorExpr,
+            Precedence::And => NonTerminal::AndExpr,
+            Precedence::Equality => NonTerminal::EqExpr,
+            Precedence::Comparison => NonTerminal::CmpExpr,
+            Precedence::Shift => NonTerminal::ShiftExpr,
+            Precedence::Sum => NonTerminal::AddExpr,
+            Precedence::Product => NonTerminal::MulExpr,
+            Precedence::Prefix => NonTerminal::UnaryExpr,
+            Precedence::Call => NonTerminal::PostfixExpr,
+            Precedence::Primary => NonTerminal::Primary,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Sym {
+    T(Terminal),
+    N(NonTerminal),
+}
+
+/// How the driver builds a production's value from the values of its right-hand side.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Action {
+    /// Keeps the value of the only symbol.
+    Pass,
+    Nothing,
+    EmptyProgram,
+    Program,
+    FirstItem,
+    NextItem,
+    Var,
+    VarInit,
+    Fun,
+    FunParams,
+    FirstParam,
+    NextParam,
+    EmptyBlock,
+    Block,
+    FirstStmt,
+    NextStmt,
+    VarStmt,
+    ExprStmt,
+    Assign,
+    Loop,
+    Until,
+    Return,
+    ReturnValue,
+    If,
+    IfElse,
+    IfElseIf,
+    Binary,
+    Unary,
+    Deref,
+    Call,
+    CallArgs,
+    FirstArg,
+    NextArg,
+    Literal,
+    Name,
+    Group,
+}
+
+#[derive(Debug, Clone)]
+pub struct Production {
+    pub lhs: NonTerminal,
+    pub rhs: Vec<Sym>,
+    pub action: Action,
+}
+
+fn p(lhs: NonTerminal, rhs: &[Sym], action: Action) -> Production {
+    Production {
+        lhs,
+        rhs: rhs.to_vec(),
+        action,
+    }
+}
+
+/// Numbered as in `ParserActions::Reduce`. The operator productions are
+/// generated from `precedence::OPERATORS` by `operator_productions`.
+pub fn productions() -> &'static [Production] {
+    static PRODUCTIONS: OnceLock<Vec<Production>> = OnceLock::new();
+    PRODUCTIONS.get_or_init(|| {
+        let mut productions = declarations();
+        productions.extend(operator_productions());
+        productions.extend(primaries());
+        productions
+    })
+}
+
+// Everything above expressions.
+fn declarations() -> Vec<Production> {
+    use Action as A;
+    use NonTerminal::*;
+    use Sym::{N, T};
+    use Terminal::*;
+    vec![
+        p(Program, &[N(OptNl)], A::EmptyProgram),
+        p(Program, &[N(OptNl), N(ItemList), N(OptNl)], A::Program),
+        p(OptNl, &[], A::Nothing),
+        p(OptNl, &[T(NL)], A::Nothing),
+        p(ItemList, &[N(Item)], A::FirstItem),
+        p(ItemList, &[N(ItemList), T(NL), N(Item)], A::NextItem),
+        p(Item, &[N(VarDecl)], A::Pass),
+        p(Item, &[N(FunDecl)], A::Pass),
+        p(VarDecl, &[T(Var), T(Identifier)], A::Var),
+        p(
+            VarDecl,
+            &[T(Var), T(Identifier), T(Assign), N(Expr)],
+            A::VarInit,
+        ),
+        p(
+            FunDecl,
+            &[T(Fun), T(Identifier), T(LParen), T(RParen), N(Block)],
+            A::Fun,
+        ),
+        p(
+            FunDecl,
+            &[
+                T(Fun),
+                T(Identifier),
+                T(LParen),
+                N(Params),
+                T(RParen),
+                N(Block),
+            ],
+            A::FunParams,
+        ),
+        p(Params, &[T(Identifier)], A::FirstParam),
+        p(Params, &[N(Params), T(Comma), T(Identifier)], A::NextParam),
+        p(Block, &[T(LBrace), N(OptNl), T(RBrace)], A::EmptyBlock),
+        p(
+            Block,
+            &[T(LBrace), N(OptNl), N(StmtList), N(OptNl), T(RBrace)],
+            A::Block,
+        ),
+        p(StmtList, &[N(Stmt)], A::FirstStmt),
+        p(StmtList, &[N(StmtList), T(NL), N(Stmt)], A::NextStmt),
+        p(Stmt, &[N(VarDecl)], A::VarStmt),
+        p(Stmt, &[N(Expr)], A::ExprStmt),
+        p(Stmt, &[N(Expr), T(Assign), N(Expr)], A::Assign),
+        p(Stmt, &[N(IfStmt)], A::Pass),
+        p(Stmt, &[T(Loop), N(Block)], A::Loop),
+        p(Stmt, &[T(Until), N(Expr)], A::Until),
+        p(Stmt, &[T(Return)], A::Return),
+        p(Stmt, &[T(Return), N(Expr)], A::ReturnValue),
+        p(IfStmt, &[T(If), N(Expr), N(Block)], A::If),
+        p(
+            IfStmt,
+            &[T(If), N(Expr), N(Block), T(Else), N(Block)],
+            A::IfElse,
+        ),
+        p(
+            IfStmt,
+            &[T(If), N(Expr), N(Block), T(Else), N(IfStmt)],
+            A::IfElseIf,
+        ),
+    ]
+}
+
+// One nonterminal per precedence level in `OPERATORS`, loosest first, each
+// deriving the operators at its level and the next tighter level.
+fn operator_productions() -> Vec<Production> {
+    use Sym::{N, T};
+    let mut levels = vec![Precedence::Lowest];
+    for op in &OPERATORS {
+        if levels.last() != Some(&op.precedence) {
+            levels.push(op.precedence);
+        }
+    }
+    levels.push(Precedence::Primary);
+    let mut productions = Vec::new();
+    for pair in levels.windows(2) {
+        let (this, tighter) = (NonTerminal::level(pair[0]), NonTerminal::level(pair[1]));
+        for op in OPERATORS.iter().filter(|op| op.precedence == pair[0]) {
+            let token = T(Terminal::syntax(op.token));
+            match op.fixity {
+                Fixity::Infix(Associativity::Left) => {
+                    productions.push(p(this, &[N(this), token, N(tighter)], Action::Binary))
+                }
+                Fixity::Infix(Associativity::Right) => {
+                    productions.push(p(this, &[N(tighter), token, N(this)], Action::Binary))
+                }
+                Fixity::Prefix => {
+                    let action = match op.token {
+                        SyntaxToken::Dot => Action::Deref,
+                        _ => Action::Unary,
+                    };
+                    productions.push(p(this, &[token, N(this)], action));
+                }
+                // Calls, the only postfix operator.
+                Fixity::Postfix => {
+                    let close = T(Terminal::RParen);
+                    productions.push(p(this, &[N(this), token, close], Action::Call));
+                    productions.push(p(
+                        this,
+                        &[N(this), token, N(NonTerminal::Args), close],
+                        Action::CallArgs,
+                    ));
+                }
+            }
+        }
+        productions.push(p(this, &[N(tighter)], Action::Pass));
+    }
+    productions
+}
+
+// Call arguments and the operands no operator is part of.
+fn primaries() -> Vec<Production> {
+    use Action as A;
+    use NonTerminal::*;
+    use Sym::{N, T};
+    use Terminal::*;
+    vec![
+        p(Args, &[N(Expr)], A::FirstArg),
+        p(Args, &[N(Args), T(Comma), N(Expr)], A::NextArg),
+        p(Primary, &[T(Integer)], A::Literal),
+        p(Primary, &[T(String)], A::Literal),
+        p(Primary, &[T(Character)], A::Literal),
+        p(Primary, &[T(Identifier)], A::Name),
+        p(Primary, &[T(LParen), N(Expr), T(RParen)], A::Group),
+    ]
+}
+
+/// `productions()` as a `Grammar` to build tables from.
+pub fn grammar() -> Grammar {
+    let symbol = |sym: &Sym| match *sym {
+        Sym::T(t) => Symbol::Terminal(t as usize),
+        Sym::N(n) => Symbol::NonTerminal(n as usize),
+    };
+    Grammar {
+        terminals: Terminal::ALL.iter().map(|t| t.name().to_owned()).collect(),
+        nonterminals: NonTerminal::ALL
+            .iter()
+            .map(|n| format!("{:?}", n))
+            .collect(),
+        rules: productions()
+            .iter()
+            .map(|p| Rule {
+                lhs: p.lhs as usize,
+                rhs: p.rhs.iter().map(symbol).collect(),
+            })
+            .collect(),
+        start: NonTerminal::Program as usize,
+        eof: Terminal::Eof as usize,
+    }
+}
@@ -0,0 +1,461 @@
+//! An LALR(1) table builder. The build script includes this file to generate
+//! the parser's tables, so it must only use `std`.
+
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Symbol {
+    Terminal(usize),
+    NonTerminal(usize),
+}
+
+/// `lhs -> rhs`, with symbols numbered as in their `Grammar`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Rule {
+    pub lhs: usize,
+    pub rhs: Vec<Symbol>,
+}
+
+/// A context-free grammar. Names are only used to describe conflicts.
+#[derive(Debug, Clone)]
+pub struct Grammar {
+    pub terminals: Vec<String>,
+    pub nonterminals: Vec<String>,
+    pub rules: Vec<Rule>,
+    /// The nonterminal a whole input reduces to.
+    pub start: usize,
+    /// The terminal that ends every input.
+    pub eof: usize,
+}
+
+/// What the parser does on reading a terminal in some state.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ParserActions {
+    /// Pushes the terminal and moves to the given state.
+    Shift(usize),
+    /// Pops the right-hand side of the given rule and pushes its left-hand side.
+    Reduce(usize),
+    Accept,
+}
+
+/// Two or more actions for the same state and terminal.
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub state: usize,
+    pub terminal: String,
+    pub actions: Vec<ParserActions>,
+    /// The items of the state that call for the actions, with their lookaheads.
+    pub items: Vec<String>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shifts = self
+            .actions
+            .iter()
+            .any(|a| matches!(a, ParserActions::Shift(_)));
+        let kind = if shifts {
+            "shift/reduce"
+        } else {
+            "reduce/reduce"
+        };
+        writeln!(
+            f,
+            "{} conflict in state {} on {}:",
+            kind, self.state, self.terminal
+        )?;
+        for item in &self.items {
+            writeln!(f, "    {}", item)?;
+        }
+        Ok(())
+    }
+}
+
+/// The ACTION and GOTO tables of an LALR(1) parser, indexed by state.
+#[derive(Debug, Clone)]
+pub struct Table {
+    /// Indexed by terminal; `None` is a syntax error.
+    pub actions: Vec<Vec<Option<ParserActions>>>,
+    /// The state to move to after reducing to a nonterminal.
+    pub gotos: Vec<Vec<Option<usize>>>,
+    /// Cells with more than one action keep the first one and are listed here.
+    pub conflicts: Vec<Conflict>,
+}
+
+// A rule and how much of its right-hand side has been read.
+type Item = (usize, usize);
+
+// Terminal sets are bitsets, so a grammar may have at most 128 terminals.
+type Terminals = u128;
+
+struct State {
+    // The kernel items come first, followed by the rest of the closure.
+    items: Vec<Item>,
+    lookaheads: Vec<Terminals>,
+    transitions: HashMap<Symbol, usize>,
+}
+
+impl Table {
+    /// Builds the LR(0) automaton of `grammar`, then computes LALR(1)
+    /// lookaheads by propagating them along its transitions until they settle.
+    pub fn build(grammar: &Grammar) -> Table {
+        assert!(grammar.terminals.len() <= Terminals::BITS as usize);
+        // The augmented rule `start' -> start` is numbered after the others.
+        let accept_rule = grammar.rules.len();
+        let mut rules = grammar.rules.clone();
+        rules.push(Rule {
+            lhs: grammar.nonterminals.len(),
+            rhs: vec![Symbol::NonTerminal(grammar.start)],
+        });
+        let builder = Builder::new(rules, grammar.nonterminals.len() + 1);
+        let mut states = builder.states(accept_rule);
+        builder.propagate_lookaheads(&mut states, accept_rule, grammar.eof);
+
+        let mut table = Table {
+            actions: vec![vec![None; grammar.terminals.len()]; states.len()],
+            gotos: vec![vec![None; grammar.nonterminals.len()]; states.len()],
+            conflicts: Vec::new(),
+        };
+        for (index, state) in states.iter().enumerate() {
+            let mut cells: Vec<Vec<(ParserActions, Item, Terminals)>> =
+                vec![Vec::new(); grammar.terminals.len()];
+            for (&(rule, dot), &lookahead) in state.items.iter().zip(&state.lookaheads) {
+                let rhs = &builder.rules[rule].rhs;
+                match rhs.get(dot) {
+                    Some(&Symbol::Terminal(t)) => {
+                        let action = ParserActions::Shift(state.transitions[&Symbol::Terminal(t)]);
+                        cells[t].push((action, (rule, dot), lookahead));
+                    }
+                    Some(Symbol::NonTerminal(_)) => {}
+                    None if rule == accept_rule => {
+                        cells[grammar.eof].push((ParserActions::Accept, (rule, dot), lookahead));
+                    }
+                    None => {
+                        for (t, cell) in cells.iter_mut().enumerate() {
+                            if lookahead & (1 << t) != 0 {
+                                cell.push((ParserActions::Reduce(rule), (rule, dot), lookahead));
+                            }
+                        }
+                    }
+                }
+            }
+            for (t, cell) in cells.into_iter().enumerate() {
+                let mut actions: Vec<ParserActions> = Vec::new();
+                for (action, _, _) in &cell {
+                    if !actions.contains(action) {
+                        actions.push(*action);
+                    }
+                }
+                table.actions[index][t] = actions.first().copied();
+                if actions.len() > 1 {
+                    table.conflicts.push(Conflict {
+                        state: index,
+                        terminal: grammar.terminals[t].clone(),
+                        actions,
+                        items: cell
+                            .iter()
+                            .map(|&(_, item, lookahead)| {
+                                describe_item(grammar, &builder.rules, item, lookahead)
+                            })
+                            .collect(),
+                    });
+                }
+            }
+            for (symbol, &target) in &state.transitions {
+                if let Symbol::NonTerminal(n) = *symbol {
+                    table.gotos[index][n] = Some(target);
+                }
+            }
+        }
+        table
+    }
+}
+
+// Renders an item like `Sum -> Sum . '+' Product  ['+', newline]`.
+fn describe_item(
+    grammar: &Grammar,
+    rules: &[Rule],
+    (rule, dot): Item,
+    lookahead: Terminals,
+) -> String {
+    let name = |symbol: &Symbol| match *symbol {
+        Symbol::Terminal(t) => grammar.terminals[t].as_str(),
+        Symbol::NonTerminal(n) => grammar
+            .nonterminals
+            .get(n)
+            .map_or("<start>", String::as_str),
+    };
+    let rule = &rules[rule];
+    let mut text = format!("{} ->", name(&Symbol::NonTerminal(rule.lhs)));
+    for (i, symbol) in rule.rhs.iter().enumerate() {
+        if i == dot {
+            text.push_str(" .");
+        }
+        text.push(' ');
+        text.push_str(name(symbol));
+    }
+    if dot == rule.rhs.len() {
+        text.push_str(" .");
+    }
+    let lookahead = (0..grammar.terminals.len())
+        .filter(|t| lookahead & (1 << t) != 0)
+        .map(|t| grammar.terminals[t].as_str())
+        .collect::<Vec<_>>();
+    format!("{}  [{}]", text, lookahead.join(", "))
+}
+
+struct Builder {
+    rules: Vec<Rule>,
+    nullable: Vec<bool>,
+    first: Vec<Terminals>,
+}
+
+impl Builder {
+    fn new(rules: Vec<Rule>, nonterminals: usize) -> Self {
+        let mut builder = Builder {
+            rules,
+            nullable: vec![false; nonterminals],
+            first: vec![0; nonterminals],
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in &builder.rules {
+                let (first, nullable) = builder.first_of(&rule.rhs);
+                if first & !builder.first[rule.lhs] != 0 || nullable && !builder.nullable[rule.lhs]
+                {
+                    builder.first[rule.lhs] |= first;
+                    builder.nullable[rule.lhs] |= nullable;
+                    changed = true;
+                }
+            }
+        }
+        builder
+    }
+
+    // The terminals `symbols` can start with, and whether they can be empty.
+    fn first_of(&self, symbols: &[Symbol]) -> (Terminals, bool) {
+        let mut first = 0;
+        for symbol in symbols {
+            match *symbol {
+                Symbol::Terminal(t) => return (first | 1 << t, false),
+                Symbol::NonTerminal(n) => {
+                    first |= self.first[n];
+                    if !self.nullable[n] {
+                        return (first, false);
+                    }
+                }
+            }
+        }
+        (first, true)
+    }
+
+    fn closure(&self, kernel: Vec<Item>) -> Vec<Item> {
+        let mut items = kernel;
+        let mut i = 0;
+        while i < items.len() {
+            let (rule, dot) = items[i];
+            if let Some(&Symbol::NonTerminal(n)) = self.rules[rule].rhs.get(dot) {
+                for (r, candidate) in self.rules.iter().enumerate() {
+                    if candidate.lhs == n && !items.contains(&(r, 0)) {
+                        items.push((r, 0));
+                    }
+                }
+            }
+            i += 1;
+        }
+        items
+    }
+
+    // The LR(0) automaton, with state 0 as the start.
+    fn states(&self, accept_rule: usize) -> Vec<State> {
+        let mut states = Vec::new();
+        let mut by_kernel: HashMap<Vec<Item>, usize> = HashMap::new();
+        let start = vec![(accept_rule, 0)];
+        by_kernel.insert(start.clone(), 0);
+        let mut pending = vec![start];
+        while let Some(kernel) = pending.pop() {
+            let index = by_kernel[&kernel];
+            let items = self.closure(kernel);
+            // Symbols in the order they are first read, so state numbers are stable.
+            let mut kernels: Vec<(Symbol, Vec<Item>)> = Vec::new();
+            for &(rule, dot) in &items {
+                if let Some(&symbol) = self.rules[rule].rhs.get(dot) {
+                    match kernels.iter_mut().find(|(s, _)| *s == symbol) {
+                        Some((_, kernel)) => kernel.push((rule, dot + 1)),
+                        None => kernels.push((symbol, vec![(rule, dot + 1)])),
+                    }
+                }
+            }
+            let mut transitions = HashMap::new();
+            for (symbol, kernel) in kernels {
+                let next = by_kernel.len();
+                let target = *by_kernel.entry(kernel.clone()).or_insert_with(|| {
+                    pending.push(kernel);
+                    next
+                });
+                transitions.insert(symbol, target);
+            }
+            if states.len() <= index {
+                states.resize_with(index + 1, || None);
+            }
+            states[index] = Some(State {
+                lookaheads: vec![0; items.len()],
+                items,
+                transitions,
+            });
+        }
+        states.into_iter().map(Option::unwrap).collect()
+    }
+
+    fn propagate_lookaheads(&self, states: &mut [State], accept_rule: usize, eof: usize) {
+        let index: Vec<HashMap<Item, usize>> = states
+            .iter()
+            .map(|s| {
+                s.items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &item)| (item, i))
+                    .collect()
+            })
+            .collect();
+        states[0].lookaheads[index[0][&(accept_rule, 0)]] = 1 << eof;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for s in 0..states.len() {
+                for i in 0..states[s].items.len() {
+                    let (rule, dot) = states[s].items[i];
+                    let lookahead = states[s].lookaheads[i];
+                    let rhs = &self.rules[rule].rhs;
+                    let Some(&symbol) = rhs.get(dot) else {
+                        continue;
+                    };
+                    // Within the state, to the items the closure added for `symbol`.
+                    if let Symbol::NonTerminal(n) = symbol {
+                        let (first, nullable) = self.first_of(&rhs[dot + 1..]);
+                        let added = if nullable { first | lookahead } else { first };
+                        for (j, &(r, d)) in states[s].items.iter().enumerate() {
+                            if d == 0
+                                && self.rules[r].lhs == n
+                                && added & !states[s].lookaheads[j] != 0
+                            {
+                                states[s].lookaheads[j] |= added;
+                                changed = true;
+                            }
+                        }
+                    }
+                    // Across the transition, to the item with `symbol` read.
+                    let target = states[s].transitions[&symbol];
+                    let j = index[target][&(rule, dot + 1)];
+                    if lookahead & !states[target].lookaheads[j] != 0 {
+                        states[target].lookaheads[j] |= lookahead;
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // E -> E + E | n, which is ambiguous, and E' -> E' + n | n, which isn't.
+    fn grammar(ambiguous: bool) -> Grammar {
+        let (plus, n, eof) = (Symbol::Terminal(0), Symbol::Terminal(1), 2);
+        let e = Symbol::NonTerminal(0);
+        let rhs = if ambiguous {
+            vec![e, plus, e]
+        } else {
+            vec![e, plus, n]
+        };
+        Grammar {
+            terminals: vec!["'+'".into(), "n".into(), "end of file".into()],
+            nonterminals: vec!["E".into()],
+            rules: vec![
+                Rule { lhs: 0, rhs },
+                Rule {
+                    lhs: 0,
+                    rhs: vec![n],
+                },
+            ],
+            start: 0,
+            eof,
+        }
+    }
+
+    // Runs the table over a sequence of terminals, returning the rules reduced by.
+    fn run(table: &Table, grammar: &Grammar, input: &[usize]) -> Option<Vec<usize>> {
+        let mut stack = vec![0];
+        let mut input = input.iter().copied().chain([grammar.eof]).peekable();
+        let mut reductions = Vec::new();
+        loop {
+            let state = *stack.last().unwrap();
+            match table.actions[state][*input.peek().unwrap()]? {
+                ParserActions::Shift(next) => {
+                    stack.push(next);
+                    input.next();
+                }
+                ParserActions::Reduce(rule) => {
+                    stack.truncate(stack.len() - grammar.rules[rule].rhs.len());
+                    let lhs = grammar.rules[rule].lhs;
+                    stack.push(table.gotos[*stack.last().unwrap()][lhs]?);
+                    reductions.push(rule);
+                }
+                ParserActions::Accept => return Some(reductions),
+            }
+        }
+    }
+
+    #[test]
+    fn builds_tables_that_parse() {
+        let grammar = grammar(false);
+        let table = Table::build(&grammar);
+        assert!(table.conflicts.is_empty());
+        assert_eq!(run(&table, &grammar, &[1, 0, 1, 0, 1]), Some(vec![1, 0, 0]));
+        assert_eq!(run(&table, &grammar, &[1]), Some(vec![1]));
+        assert_eq!(run(&table, &grammar, &[1, 0]), None);
+        assert_eq!(run(&table, &grammar, &[]), None);
+    }
+
+    #[test]
+    fn handles_empty_rules() {
+        // L -> L n | <empty>
+        let grammar = Grammar {
+            terminals: vec!["n".into(), "end of file".into()],
+            nonterminals: vec!["L".into()],
+            rules: vec![
+                Rule {
+                    lhs: 0,
+                    rhs: vec![Symbol::NonTerminal(0), Symbol::Terminal(0)],
+                },
+                Rule {
+                    lhs: 0,
+                    rhs: vec![],
+                },
+            ],
+            start: 0,
+            eof: 1,
+        };
+        let table = Table::build(&grammar);
+        assert!(table.conflicts.is_empty());
+        assert_eq!(run(&table, &grammar, &[]), Some(vec![1]));
+        assert_eq!(run(&table, &grammar, &[0, 0]), Some(vec![1, 0, 0]));
+    }
+
+    #[test]
+    fn reports_conflicts_with_their_items() {
+        let table = Table::build(&grammar(true));
+        assert_eq!(table.conflicts.len(), 1);
+        assert_eq!(
+            table.conflicts[0].to_string(),
+            "shift/reduce conflict in state 4 on '+':
+    E -> E '+' E .  ['+', end of file]
+    E -> E . '+' E  ['+', end of file]
+"
+        );
+    }
+}
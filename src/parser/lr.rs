@@ -0,0 +1,718 @@
+use super::grammar::{productions, Action, Terminal};
+use super::parens::binary_op;
+use super::{ParseError, Parser, ParserActions};
+use crate::ast::{Expr, ExprKind, Fun, Item, NodeIds, Program, Stmt, StmtKind, UnaryOp, Var, ID};
+use crate::lex::token::{Token, TokenKind, TokenType};
+use crate::lex::types::{KeywordToken, LiteralToken, SyntaxToken};
+use crate::source::Span;
+
+mod table {
+    use super::ParserActions::{self, Accept, Reduce, Shift};
+    include!(concat!(env!("OUT_DIR"), "/lalr_table.rs"));
+}
+
+// The magnitude of the smallest integer, which is only written negated.
+const MIN_INTEGER: u64 = i64::MAX as u64 + 1;
+
+fn terminal(kind: TokenKind) -> Option<Terminal> {
+    let terminal = match kind {
+        TokenKind::Keyword(keyword) => match keyword {
+            KeywordToken::Var => Terminal::Var,
+            KeywordToken::Fun => Terminal::Fun,
+            KeywordToken::If => Terminal::If,
+            KeywordToken::Else => Terminal::Else,
+            KeywordToken::Loop => Terminal::Loop,
+            KeywordToken::Until => Terminal::Until,
+            KeywordToken::Return => Terminal::Return,
+            KeywordToken::Const | KeywordToken::Import => return None,
+        },
+        TokenKind::Syntax(syntax) => Terminal::syntax(syntax),
+        TokenKind::Identifier => Terminal::Identifier,
+        TokenKind::Integer => Terminal::Integer,
+        TokenKind::String => Terminal::String,
+        TokenKind::Character => Terminal::Character,
+        TokenKind::NL => Terminal::NL,
+        TokenKind::Eof => Terminal::Eof,
+        TokenKind::Error | TokenKind::DocComment => return None,
+    };
+    Some(terminal)
+}
+
+// The inverse of `terminal`.
+fn kind(terminal: Terminal) -> TokenKind {
+    match terminal {
+        Terminal::Var => TokenKind::Keyword(KeywordToken::Var),
+        Terminal::Fun => TokenKind::Keyword(KeywordToken::Fun),
+        Terminal::If => TokenKind::Keyword(KeywordToken::If),
+        Terminal::Else => TokenKind::Keyword(KeywordToken::Else),
+        Terminal::Loop => TokenKind::Keyword(KeywordToken::Loop),
+        Terminal::Until => TokenKind::Keyword(KeywordToken::Until),
+        Terminal::Return => TokenKind::Keyword(KeywordToken::Return),
+        Terminal::LBrace => TokenKind::Syntax(SyntaxToken::LBrace),
+        Terminal::RBrace => TokenKind::Syntax(SyntaxToken::RBrace),
+        Terminal::LParen => TokenKind::Syntax(SyntaxToken::LParen),
+        Terminal::RParen => TokenKind::Syntax(SyntaxToken::RParen),
+        Terminal::Assign => TokenKind::Syntax(SyntaxToken::Assign),
+        Terminal::Comma => TokenKind::Syntax(SyntaxToken::Comma),
+        Terminal::Dot => TokenKind::Syntax(SyntaxToken::Dot),
+        Terminal::Minus => TokenKind::Syntax(SyntaxToken::Minus),
+        Terminal::Not => TokenKind::Syntax(SyntaxToken::Not),
+        Terminal::Plus => TokenKind::Syntax(SyntaxToken::Plus),
+        Terminal::Times => TokenKind::Syntax(SyntaxToken::Times),
+        Terminal::Slash => TokenKind::Syntax(SyntaxToken::Slash),
+        Terminal::Mod => TokenKind::Syntax(SyntaxToken::Mod),
+        Terminal::And => TokenKind::Syntax(SyntaxToken::And),
+        Terminal::Or => TokenKind::Syntax(SyntaxToken::Or),
+        Terminal::Xor => TokenKind::Syntax(SyntaxToken::Xor),
+        Terminal::Eq => TokenKind::Syntax(SyntaxToken::Eq),
+        Terminal::Neq => TokenKind::Syntax(SyntaxToken::Neq),
+        Terminal::Lt => TokenKind::Syntax(SyntaxToken::Lt),
+        Terminal::Leq => TokenKind::Syntax(SyntaxToken::Leq),
+        Terminal::Gt => TokenKind::Syntax(SyntaxToken::Gt),
+        Terminal::Geq => TokenKind::Syntax(SyntaxToken::Geq),
+        Terminal::LShift => TokenKind::Syntax(SyntaxToken::LShift),
+        Terminal::RShift => TokenKind::Syntax(SyntaxToken::RShift),
+        Terminal::Identifier => TokenKind::Identifier,
+        Terminal::Integer => TokenKind::Integer,
+        Terminal::String => TokenKind::String,
+        Terminal::Character => TokenKind::Character,
+        Terminal::NL => TokenKind::NL,
+        Terminal::Eof => TokenKind::Eof,
+    }
+}
+
+/// An entry of `Parser::stack`: a state of the LALR(1) automaton and the
+/// value of the symbol that led to it.
+#[derive(Debug)]
+pub(super) struct Frame {
+    state: usize,
+    /// `None` for a symbol that matched no tokens.
+    span: Option<Span>,
+    value: Value,
+}
+
+#[derive(Debug)]
+enum Value {
+    Nothing,
+    /// A shifted token, with the doc comments written above it.
+    Token(Token, Vec<String>),
+    /// An expression, and the literal token if it is an integer literal that
+    /// only fits once negated.
+    Expr(Expr, Option<Box<Token>>),
+    Exprs(Vec<Expr>),
+    Stmt(Stmt),
+    Stmts(Vec<Stmt>),
+    Params(Vec<ID>),
+    Var(Var),
+    Fun(Fun),
+    Items(Vec<Item>),
+}
+
+// The grammar's productions and the driver's actions disagree if these fail.
+impl Value {
+    fn token(self) -> Token {
+        match self {
+            Value::Token(token, _) => token,
+            value => unreachable!("expected a token, got {:?}", value),
+        }
+    }
+
+    fn span(&self) -> Span {
+        match self {
+            Value::Token(token, _) => token.span(),
+            value => unreachable!("expected a token, got {:?}", value),
+        }
+    }
+
+    fn name(self) -> String {
+        match self.token().token_type() {
+            TokenType::IdentifierToken(name) => name.as_str().to_owned(),
+            token => unreachable!("expected an identifier, got {:?}", token),
+        }
+    }
+
+    fn expr(self) -> Result<Expr, ParseError> {
+        match self {
+            Value::Expr(_, Some(found)) => Err(ParseError::IntegerOverflow { found }),
+            Value::Expr(expr, None) => Ok(expr),
+            value => unreachable!("expected an expression, got {:?}", value),
+        }
+    }
+
+    fn exprs(self) -> Vec<Expr> {
+        match self {
+            Value::Exprs(exprs) => exprs,
+            value => unreachable!("expected arguments, got {:?}", value),
+        }
+    }
+
+    fn stmt(self) -> Stmt {
+        match self {
+            Value::Stmt(stmt) => stmt,
+            value => unreachable!("expected a statement, got {:?}", value),
+        }
+    }
+
+    fn stmts(self) -> Vec<Stmt> {
+        match self {
+            Value::Stmts(stmts) => stmts,
+            value => unreachable!("expected statements, got {:?}", value),
+        }
+    }
+
+    fn params(self) -> Vec<ID> {
+        match self {
+            Value::Params(params) => params,
+            value => unreachable!("expected parameters, got {:?}", value),
+        }
+    }
+
+    fn item(self) -> Item {
+        match self {
+            Value::Var(var) => Item::Var(var),
+            Value::Fun(fun) => Item::Fun(fun),
+            value => unreachable!("expected a declaration, got {:?}", value),
+        }
+    }
+
+    fn items(self) -> Vec<Item> {
+        match self {
+            Value::Items(items) => items,
+            value => unreachable!("expected declarations, got {:?}", value),
+        }
+    }
+}
+
+// What a reduction builds a node from: its span and the next id.
+struct Node<'a> {
+    span: Span,
+    ids: &'a mut NodeIds,
+}
+
+impl Node<'_> {
+    fn expr(&mut self, kind: ExprKind) -> Value {
+        Value::Expr(Expr::new(self.ids.next_id(), self.span, kind), None)
+    }
+
+    fn stmt(&mut self, kind: StmtKind) -> Value {
+        Value::Stmt(Stmt::new(self.ids.next_id(), self.span, kind))
+    }
+
+    fn id(&mut self, name: Value) -> ID {
+        ID {
+            id: self.ids.next_id(),
+            span: self.span,
+            name: name.name(),
+        }
+    }
+
+    fn fun(&mut self, fun: Value, name: Value, params: Vec<ID>, body: Value) -> Value {
+        let Value::Token(_, docs) = fun else {
+            unreachable!("expected 'fun', got {:?}", fun)
+        };
+        let fun = Fun {
+            id: self.ids.next_id(),
+            span: self.span,
+            docs,
+            name: name.name(),
+            params,
+            body: body.stmts(),
+        };
+        Value::Fun(fun)
+    }
+
+    fn var(&mut self, var: Value, name: Value, value: Option<Expr>) -> Value {
+        let Value::Token(_, docs) = var else {
+            unreachable!("expected 'var', got {:?}", var)
+        };
+        let var = Var {
+            id: self.ids.next_id(),
+            span: self.span,
+            docs,
+            name: name.name(),
+            value,
+        };
+        Value::Var(var)
+    }
+}
+
+fn reduce(action: Action, values: Vec<Value>, mut node: Node) -> Result<Value, ParseError> {
+    let mut values = values.into_iter();
+    let mut next = || values.next().unwrap();
+    let value = match action {
+        Action::Pass => next(),
+        Action::Nothing => Value::Nothing,
+        Action::EmptyProgram => Value::Items(vec![]),
+        Action::Program => {
+            next();
+            next()
+        }
+        Action::FirstItem => Value::Items(vec![next().item()]),
+        Action::NextItem => {
+            let mut items = next().items();
+            next();
+            items.push(next().item());
+            Value::Items(items)
+        }
+        Action::Var => node.var(next(), next(), None),
+        Action::VarInit => {
+            let (keyword, name) = (next(), next());
+            next();
+            let value = next().expr()?;
+            node.var(keyword, name, Some(value))
+        }
+        Action::Fun => {
+            let (keyword, name) = (next(), next());
+            next();
+            next();
+            node.fun(keyword, name, vec![], next())
+        }
+        Action::FunParams => {
+            let (keyword, name) = (next(), next());
+            next();
+            let params = next().params();
+            next();
+            node.fun(keyword, name, params, next())
+        }
+        Action::FirstParam => Value::Params(vec![node.id(next())]),
+        Action::NextParam => {
+            let mut params = next().params();
+            next();
+            let name = next();
+            // The span of the parameter alone, not of the whole list.
+            node.span = name.span();
+            params.push(node.id(name));
+            Value::Params(params)
+        }
+        Action::EmptyBlock => Value::Stmts(vec![]),
+        Action::Block => {
+            next();
+            next();
+            next()
+        }
+        Action::FirstStmt => Value::Stmts(vec![next().stmt()]),
+        Action::NextStmt => {
+            let mut stmts = next().stmts();
+            next();
+            stmts.push(next().stmt());
+            Value::Stmts(stmts)
+        }
+        Action::VarStmt => match next() {
+            Value::Var(var) => node.stmt(StmtKind::Var(var)),
+            value => unreachable!("expected a variable, got {:?}", value),
+        },
+        Action::ExprStmt => {
+            let expr = next().expr()?;
+            node.stmt(StmtKind::Expr(expr))
+        }
+        Action::Assign => {
+            let target = next().expr()?;
+            next();
+            let value = next().expr()?;
+            node.stmt(StmtKind::Assign { target, value })
+        }
+        Action::Loop => {
+            next();
+            let body = next().stmts();
+            node.stmt(StmtKind::Loop(body))
+        }
+        Action::Until => {
+            next();
+            let cond = next().expr()?;
+            node.stmt(StmtKind::Until(cond))
+        }
+        Action::Return => node.stmt(StmtKind::Return(None)),
+        Action::ReturnValue => {
+            next();
+            let value = next().expr()?;
+            node.stmt(StmtKind::Return(Some(value)))
+        }
+        Action::If | Action::IfElse | Action::IfElseIf => {
+            next();
+            let cond = next().expr()?;
+            let then_body = next().stmts();
+            let else_body = match action {
+                Action::IfElse => {
+                    next();
+                    Some(next().stmts())
+                }
+                Action::IfElseIf => {
+                    next();
+                    Some(vec![next().stmt()])
+                }
+                _ => None,
+            };
+            node.stmt(StmtKind::If {
+                cond,
+                then_body,
+                else_body,
+            })
+        }
+        Action::Binary => {
+            let lhs = next().expr()?;
+            let op = match next().token().kind() {
+                TokenKind::Syntax(token) => binary_op(token).unwrap(),
+                kind => unreachable!("expected an operator, got {}", kind),
+            };
+            let rhs = next().expr()?;
+            node.expr(ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)))
+        }
+        Action::Unary => {
+            let op = match next().token().kind() {
+                TokenKind::Syntax(SyntaxToken::Minus) => UnaryOp::Neg,
+                _ => UnaryOp::Not,
+            };
+            let operand = match (op, next()) {
+                (UnaryOp::Neg, Value::Expr(literal, Some(_))) => literal,
+                (_, operand) => operand.expr()?,
+            };
+            node.expr(ExprKind::Unary(op, Box::new(operand)))
+        }
+        Action::Deref => {
+            next();
+            let operand = next().expr()?;
+            node.expr(ExprKind::Deref(Box::new(operand)))
+        }
+        Action::Call => {
+            let callee = next().expr()?;
+            node.expr(ExprKind::Call(Box::new(callee), vec![]))
+        }
+        Action::CallArgs => {
+            let callee = next().expr()?;
+            next();
+            let args = next().exprs();
+            node.expr(ExprKind::Call(Box::new(callee), args))
+        }
+        Action::FirstArg => Value::Exprs(vec![next().expr()?]),
+        Action::NextArg => {
+            let mut args = next().exprs();
+            next();
+            args.push(next().expr()?);
+            Value::Exprs(args)
+        }
+        Action::Literal => {
+            let token = next().token();
+            match token.token_type() {
+                TokenType::Literal(LiteralToken::Integer { value, .. }) => {
+                    let Value::Expr(expr, _) = node.expr(ExprKind::Integer(*value)) else {
+                        unreachable!()
+                    };
+                    let overflow = (*value >= MIN_INTEGER).then(|| Box::new(token.clone()));
+                    Value::Expr(expr, overflow)
+                }
+                TokenType::Literal(LiteralToken::String { value, .. }) => {
+                    node.expr(ExprKind::String(value.clone()))
+                }
+                TokenType::Literal(LiteralToken::Character { value, .. }) => {
+                    node.expr(ExprKind::Character(*value))
+                }
+                token => unreachable!("expected a literal, got {:?}", token),
+            }
+        }
+        Action::Name => {
+            let name = next().name();
+            node.expr(ExprKind::Var(name))
+        }
+        Action::Group => {
+            next();
+            let inner = next().expr()?;
+            node.expr(ExprKind::Group(Box::new(inner)))
+        }
+    };
+    Ok(value)
+}
+
+/// A shift-reduce parser driven by the LALR(1) tables the build script
+/// generates from `grammar::productions()`.
+impl Parser {
+    pub(super) fn lr_program(&mut self) -> Result<Program, ParseError> {
+        self.stack.clear();
+        self.stack.push(Frame {
+            state: 0,
+            span: None,
+            value: Value::Nothing,
+        });
+        let mut parens = 0usize;
+        let (mut token, mut docs) = self.lr_token(true);
+        loop {
+            let state = self.stack.last().unwrap().state;
+            let action = terminal(token.kind()).and_then(|t| table::ACTIONS[state][t as usize]);
+            match action {
+                Some(ParserActions::Shift(next)) => {
+                    match token.kind() {
+                        TokenKind::Syntax(SyntaxToken::LParen) => parens += 1,
+                        TokenKind::Syntax(SyntaxToken::RParen) => parens -= 1,
+                        _ => {}
+                    }
+                    self.tokens.set_newlines_significant(parens == 0);
+                    // Doc comments can only start a line, or follow a `{`.
+                    let line_start = matches!(
+                        token.kind(),
+                        TokenKind::NL | TokenKind::Syntax(SyntaxToken::LBrace)
+                    );
+                    self.stack.push(Frame {
+                        state: next,
+                        span: Some(token.span()),
+                        value: Value::Token(token, docs),
+                    });
+                    (token, docs) = self.lr_token(line_start);
+                }
+                Some(ParserActions::Reduce(rule)) => {
+                    let production = &productions()[rule];
+                    let frames = self
+                        .stack
+                        .split_off(self.stack.len() - production.rhs.len());
+                    let span = frames
+                        .iter()
+                        .filter_map(|frame| frame.span)
+                        .reduce(Span::to);
+                    let values = frames.into_iter().map(|frame| frame.value).collect();
+                    let node = Node {
+                        span: span.unwrap_or_default(),
+                        ids: &mut self.ids,
+                    };
+                    let value = reduce(production.action, values, node)?;
+                    let state = self.stack.last().unwrap().state;
+                    let next = table::GOTOS[state][production.lhs as usize].unwrap();
+                    self.stack.push(Frame {
+                        state: next.into(),
+                        span,
+                        value,
+                    });
+                }
+                Some(ParserActions::Accept) => {
+                    let items = self.stack.pop().unwrap().value.items();
+                    self.stack.clear();
+                    return Ok(Program { items });
+                }
+                None => {
+                    self.tokens.set_newlines_significant(true);
+                    return Err(self.lr_error(state, &token));
+                }
+            }
+        }
+    }
+
+    // The next token, with the doc comments before it if it starts a line. Runs of
+    // NL tokens and an NL token before `else` are dropped, as the grammar expects.
+    fn lr_token(&mut self, line_start: bool) -> (Token, Vec<String>) {
+        let docs = if line_start {
+            self.tokens.doc_comments()
+        } else {
+            vec![]
+        };
+        let token = self.tokens.bump();
+        if token.kind() == TokenKind::NL {
+            self.tokens.skip_newlines();
+            let before_else = self.tokens.checkpoint();
+            self.tokens.doc_comments();
+            if self.tokens.at(TokenKind::Keyword(KeywordToken::Else)) {
+                return (self.tokens.bump(), docs);
+            }
+            self.tokens.rewind(before_else);
+        }
+        (token, docs)
+    }
+
+    fn lr_error(&self, state: usize, found: &Token) -> ParseError {
+        let mut valid = Terminal::ALL
+            .iter()
+            .filter(|&&t| table::ACTIONS[state][t as usize].is_some());
+        match (valid.next(), valid.next()) {
+            (Some(&expected), None) => ParseError::unexpected(kind(expected), found),
+            _ => ParseError::expected(table::EXPECTED[state], found),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::Lexer;
+    use crate::parser::{Fixity, Operator, OPERATORS};
+    use proptest::prelude::*;
+
+    fn both(source: &str) -> (Result<Program, String>, Result<Program, String>) {
+        let descent = Parser::new(Lexer::new().lex_str(source).unwrap()).parse();
+        let lr = Parser::new(Lexer::new().lex_str(source).unwrap()).parse_lr();
+        (
+            descent.map_err(|e| e.to_string()),
+            lr.map_err(|e| e.to_string()),
+        )
+    }
+
+    #[test]
+    fn builds_conflict_free_tables() {
+        let grammar = super::super::grammar::grammar();
+        let built = super::super::lalr::Table::build(&grammar);
+        assert!(built.conflicts.is_empty());
+        assert_eq!(built.actions.len(), table::STATES);
+        for (built, generated) in built.actions.iter().zip(&table::ACTIONS) {
+            assert_eq!(built, generated);
+        }
+        for t in Terminal::ALL {
+            assert_eq!(terminal(kind(t)), Some(t));
+        }
+    }
+
+    #[test]
+    fn agrees_with_the_descent_parser() {
+        let sources = [
+            std::fs::read_to_string("examples/sq.t").unwrap(),
+            String::new(),
+            "\n\n## The answer.\n\nvar x : 42\n\n\nvar y\n".to_string(),
+            "## Adds.\nfun add(a,\n        b) {\n    return add(.a,\n  .b)\n}\n".to_string(),
+            "fun f() {\n## Local.\nvar x : 'a'\nreturn\n.p : x\nf()(1)\n}".to_string(),
+            "fun f() { loop { return } }".to_string(),
+            "fun f() {\nif .a == 0 {\n    f()\n} else if .b == 0 {\n} else { g() }\nif .c {}\n\nelse {}\n}"
+                .to_string(),
+            "var x : .n * .n + 1 << 2 == x | y ^ z & !w % -(a +\n b) >= \"s\"".to_string(),
+            "var x : -9223372036854775808 - --9223372036854775807".to_string(),
+            "var x : 1 ## The answer.\nfun f() {\n    g(1,\n ## Second.\n 2)\n}".to_string(),
+            "fun f() {\n    ## TODO\n}\n".to_string(),
+            "var x\n## End of file.\n".to_string(),
+            "fun f() {\n    if x {}\n    ## Otherwise.\n    else {}\n}".to_string(),
+            "var n : 1\n## Doubles.\nfun f() {\n    n : .n * 2\n}\nvar m\nfun g() {}".to_string(),
+        ];
+        for source in &sources {
+            let (descent, lr) = both(source);
+            assert!(descent.is_ok(), "{:?}: {:?}", source, descent);
+            assert_eq!(descent, lr, "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn rejects_what_the_descent_parser_rejects() {
+        let sources = [
+            "x : 1",
+            "var x y",
+            "fun f() {\n    x :\n}",
+            "fun f(a b) {}",
+            "fun f() {\n    loop {\n",
+            "var x : 9223372036854775808",
+            "var x : -(9223372036854775808)",
+            "var x : !9223372036854775808",
+            "var x : -9223372036854775808(1)",
+            "fun f() {} fun g() {}",
+            "fun f() {\n    return 1 2\n}",
+            "var x : f(,)",
+        ];
+        for source in &sources {
+            let (descent, lr) = both(source);
+            assert!(descent.is_err(), "{:?}", source);
+            assert!(lr.is_err(), "{:?}: {:?}", source, lr);
+        }
+    }
+
+    // The grammar's operator productions are generated from `OPERATORS`, which
+    // drives the descent parser directly, so any pair of operators must parse
+    // the same way in both.
+    #[test]
+    fn agrees_on_every_operator_in_the_table() {
+        let spell = |op: &Operator| op.token.as_str();
+        let binary = OPERATORS
+            .iter()
+            .filter(|op| matches!(op.fixity, Fixity::Infix(_)))
+            .collect::<Vec<_>>();
+        let prefix = OPERATORS
+            .iter()
+            .filter(|op| op.fixity == Fixity::Prefix)
+            .collect::<Vec<_>>();
+        let mut sources = Vec::new();
+        for a in &binary {
+            sources.push(format!("var x : -a {} b(c)", spell(a)));
+            for b in &binary {
+                sources.push(format!("var x : a {} b {} c", spell(a), spell(b)));
+            }
+            for p in &prefix {
+                sources.push(format!("var x : {}a {} {}b", spell(p), spell(a), spell(p)));
+            }
+        }
+        for p in &prefix {
+            sources.push(format!("var x : {}{}a(b)", spell(p), spell(p)));
+        }
+        for source in &sources {
+            let (descent, lr) = both(source);
+            assert!(descent.is_ok(), "{:?}: {:?}", source, descent);
+            assert_eq!(descent, lr, "{:?}", source);
+        }
+        // The build script groups the binary operators of the table.
+        let (_, lr) = both("var x : a b");
+        assert!(lr.unwrap_err().starts_with("Expected an operator, "));
+    }
+
+    #[test]
+    fn reports_what_the_table_expects() {
+        // Top-level and local `var`s share states, so this also allows a `}`.
+        let (_, lr) = both("var x y");
+        assert_eq!(
+            lr.unwrap_err(),
+            "Expected '}', ':', newline or end of file, found identifier at 1:7"
+        );
+        let (_, lr) = both("fun f() {\n    x :\n}");
+        assert_eq!(
+            lr.unwrap_err(),
+            "Expected an expression, found newline at 2:8"
+        );
+        let (_, lr) = both("fun f() {\n    loop {\n");
+        assert_eq!(
+            lr.unwrap_err(),
+            "Expected an expression, 'var', 'if', 'loop', 'until', 'return' or '}', found end of file at 3:1"
+        );
+        let (_, lr) = both("fun f");
+        assert_eq!(lr.unwrap_err(), "Expected '(', found end of file at 1:6");
+    }
+
+    const WORDS: [&str; 28] = [
+        "var",
+        "fun",
+        "if",
+        "else",
+        "loop",
+        "until",
+        "return",
+        "x",
+        "f",
+        "1",
+        "9223372036854775808",
+        "'c'",
+        "\"s\"",
+        "{",
+        "}",
+        "(",
+        ")",
+        ":",
+        ",",
+        ".",
+        "-",
+        "!",
+        "+",
+        "*",
+        "<<",
+        "==",
+        "\n",
+        "## d\n",
+    ];
+
+    proptest! {
+        #[test]
+        fn agrees_on_any_tokens(words in prop::collection::vec(prop::sample::select(&WORDS[..]), 0..40)) {
+            let source = words.join(" ");
+            let (descent, lr) = both(&source);
+            prop_assert_eq!(descent.is_ok(), lr.is_ok(), "{:?}: {:?} {:?}", source, descent, lr);
+            if descent.is_ok() {
+                prop_assert_eq!(descent, lr);
+            }
+        }
+
+        #[test]
+        fn agrees_on_any_function(body in prop::collection::vec(prop::sample::select(&WORDS[..]), 0..30)) {
+            let source = format!("fun f(a, b) {{\n{}\n}}", body.join(" "));
+            let (descent, lr) = both(&source);
+            prop_assert_eq!(descent.is_ok(), lr.is_ok(), "{:?}: {:?} {:?}", source, descent, lr);
+            if descent.is_ok() {
+                prop_assert_eq!(descent, lr);
+            }
+        }
+    }
+}
@@ -1,36 +1,163 @@
-use thiserror::Error;
-use anyhow::{bail, ensure, Result};
-use crate::ast;
-use crate::ast::Program;
+mod cursor;
+mod descent;
+mod grammar;
+mod lalr;
+mod lr;
+mod parens;
+mod precedence;
+
+use crate::ast::{NodeIds, Program};
+use crate::diagnostics::{Diagnostic, Label, Report, Suggestion};
 use crate::lex::lexer::TokenStream;
-use crate::lex::token::Token;
+use crate::lex::token::{Token, TokenKind};
+use anyhow::Result;
+use thiserror::Error;
+
+pub use cursor::{Checkpoint, TokenCursor};
+pub use grammar::grammar;
+pub use lalr::{Conflict, Grammar, ParserActions, Rule, Symbol, Table};
+pub use parens::{binary, binary_op, binary_token, needs_parens, Operand};
+pub use precedence::{infix, prefix, Associativity, Fixity, Operator, Precedence, OPERATORS};
 
 #[derive(Debug, Error)]
-enum ParseError {
-    #[error("Unexpected token")]
-    UnexpectedToken,
-    #[error("Unexpected end of file")]
-    UnexpectedEOF,
+pub enum ParseError {
+    #[error("Expected {expected}, found {} at {}:{}", .found.kind(), .found.line_no(), .found.col_no())]
+    UnexpectedToken {
+        expected: TokenKind,
+        found: Box<Token>,
+    },
+    #[error("Expected {expected}, found end of file at {line_no}:{col_no}")]
+    UnexpectedEOF {
+        expected: TokenKind,
+        line_no: usize,
+        col_no: usize,
+    },
+    #[error("Expected {expected}, found {} at {}:{}", .found.kind(), .found.line_no(), .found.col_no())]
+    Expected {
+        expected: &'static str,
+        found: Box<Token>,
+    },
+    #[error("Integer literal {} out of range at {}:{}", .found.text(), .found.line_no(), .found.col_no())]
+    IntegerOverflow { found: Box<Token> },
 }
 
-pub struct Parser {
-    tokens: TokenStream,
-    stack: Vec<Token>
+impl ParseError {
+    pub(crate) fn unexpected(expected: TokenKind, found: &Token) -> Self {
+        match found.kind() {
+            TokenKind::Eof => ParseError::UnexpectedEOF {
+                expected,
+                line_no: found.line_no(),
+                col_no: found.col_no(),
+            },
+            _ => ParseError::UnexpectedToken {
+                expected,
+                found: Box::new(found.clone()),
+            },
+        }
+    }
+
+    /// For what the parser expected that isn't a single kind of token, like "an expression".
+    pub(crate) fn expected(expected: &'static str, found: &Token) -> Self {
+        ParseError::Expected {
+            expected,
+            found: Box::new(found.clone()),
+        }
+    }
 }
 
-pub enum ParserActions {
-    Shift,
-    Reduce(usize),
-    Accept,
+impl Diagnostic for ParseError {
+    fn code(&self) -> &'static str {
+        match self {
+            ParseError::UnexpectedToken { .. } => "P0001",
+            ParseError::UnexpectedEOF { .. } => "P0002",
+            ParseError::Expected { .. } => "P0003",
+            ParseError::IntegerOverflow { .. } => "P0005",
+        }
+    }
+
+    fn report(&self) -> Report {
+        let report = match self {
+            ParseError::UnexpectedToken { expected, found } => suggest_insertion(
+                Report::error(format!("expected {}, found {}", expected, found.kind()))
+                    .with_label(Label::token(found, format!("expected {}", expected))),
+                *expected,
+                found.line_no(),
+                found.col_no(),
+            ),
+            ParseError::UnexpectedEOF {
+                expected,
+                line_no,
+                col_no,
+            } => suggest_insertion(
+                Report::error(format!("expected {}, found end of file", expected)).with_label(
+                    Label::primary(*line_no, *col_no, 0, format!("expected {}", expected)),
+                ),
+                *expected,
+                *line_no,
+                *col_no,
+            ),
+            ParseError::Expected { expected, found } => {
+                Report::error(format!("expected {}, found {}", expected, found.kind()))
+                    .with_label(Label::token(found, format!("expected {}", expected)))
+            }
+            ParseError::IntegerOverflow { found } => Report::error("integer literal is too large")
+                .with_label(Label::token(found, "larger than 9223372036854775807"))
+                .with_note("only -9223372036854775808 may go past the largest integer"),
+        };
+        report.with_code(self.code())
+    }
+}
+
+// Suggests inserting the expected token, if it is always spelled the same way.
+fn suggest_insertion(report: Report, expected: TokenKind, line_no: usize, col_no: usize) -> Report {
+    let text = match expected {
+        TokenKind::Keyword(k) => k.as_str(),
+        TokenKind::Syntax(s) => s.as_str(),
+        _ => return report,
+    };
+    report.with_suggestion(Suggestion::new(
+        line_no,
+        col_no,
+        0,
+        text,
+        "add the missing token",
+    ))
+}
+
+/// Parses a token stream with either a hand-written recursive-descent parser
+/// (`parse`) or a table-driven LALR(1) one (`parse_lr`). Both build the same
+/// AST, so either can check the other.
+pub struct Parser {
+    tokens: TokenCursor,
+    // Used by `parse_lr` only.
+    stack: Vec<lr::Frame>,
+    ids: NodeIds,
 }
 
 impl Parser {
     pub fn new(tokens: TokenStream) -> Self {
-        Self { tokens, stack: vec![] }
+        Self {
+            tokens: TokenCursor::new(tokens),
+            stack: vec![],
+            ids: NodeIds::new(),
+        }
     }
 
-    // This should parse a token stream into an AST.
+    /// Parses the whole token stream. Fails with a `ParseError` at the first
+    /// syntax error.
     pub fn parse(&mut self) -> Result<Program> {
-        todo!("Implement parser");
+        Ok(self.program()?)
+    }
+
+    /// Parses the whole token stream with the LALR(1) tables built from
+    /// `grammar()`. Only the wording of syntax errors differs from `parse`.
+    pub fn parse_lr(&mut self) -> Result<Program> {
+        Ok(self.lr_program()?)
+    }
+}
+
+impl AsMut<TokenCursor> for Parser {
+    fn as_mut(&mut self) -> &mut TokenCursor {
+        &mut self.tokens
     }
 }
@@ -0,0 +1,285 @@
+use super::precedence::{infix, Associativity, Fixity, Operator, Precedence};
+use crate::ast::{BinaryOp, Expr, ExprKind};
+use crate::lex::types::SyntaxToken;
+
+impl Precedence {
+    /// How tightly `expr` holds together, i.e. of its outermost operator.
+    pub fn of(expr: &Expr) -> Precedence {
+        match &expr.kind {
+            ExprKind::Binary(op, ..) => binary(*op).precedence,
+            ExprKind::Unary(..) | ExprKind::Deref(_) => Precedence::Prefix,
+            ExprKind::Call(..) => Precedence::Call,
+            ExprKind::Integer(_)
+            | ExprKind::String(_)
+            | ExprKind::Character(_)
+            | ExprKind::Var(_)
+            | ExprKind::Group(_) => Precedence::Primary,
+        }
+    }
+}
+
+/// The entry for a binary operator.
+pub fn binary(op: BinaryOp) -> &'static Operator {
+    let token = binary_token(op);
+    infix(token).unwrap()
+}
+
+/// The binary operator `token` spells, if any.
+pub fn binary_op(token: SyntaxToken) -> Option<BinaryOp> {
+    let op = match token {
+        SyntaxToken::Or => BinaryOp::Or,
+        SyntaxToken::Xor => BinaryOp::Xor,
+        SyntaxToken::And => BinaryOp::And,
+        SyntaxToken::Eq => BinaryOp::Eq,
+        SyntaxToken::Neq => BinaryOp::Neq,
+        SyntaxToken::Lt => BinaryOp::Lt,
+        SyntaxToken::Leq => BinaryOp::Leq,
+        SyntaxToken::Gt => BinaryOp::Gt,
+        SyntaxToken::Geq => BinaryOp::Geq,
+        SyntaxToken::LShift => BinaryOp::LShift,
+        SyntaxToken::RShift => BinaryOp::RShift,
+        SyntaxToken::Plus => BinaryOp::Add,
+        SyntaxToken::Minus => BinaryOp::Sub,
+        SyntaxToken::Times => BinaryOp::Mul,
+        SyntaxToken::Slash => BinaryOp::Div,
+        SyntaxToken::Mod => BinaryOp::Mod,
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// The inverse of `binary_op`.
+pub fn binary_token(op: BinaryOp) -> SyntaxToken {
+    match op {
+        BinaryOp::Or => SyntaxToken::Or,
+        BinaryOp::Xor => SyntaxToken::Xor,
+        BinaryOp::And => SyntaxToken::And,
+        BinaryOp::Eq => SyntaxToken::Eq,
+        BinaryOp::Neq => SyntaxToken::Neq,
+        BinaryOp::Lt => SyntaxToken::Lt,
+        BinaryOp::Leq => SyntaxToken::Leq,
+        BinaryOp::Gt => SyntaxToken::Gt,
+        BinaryOp::Geq => SyntaxToken::Geq,
+        BinaryOp::LShift => SyntaxToken::LShift,
+        BinaryOp::RShift => SyntaxToken::RShift,
+        BinaryOp::Add => SyntaxToken::Plus,
+        BinaryOp::Sub => SyntaxToken::Minus,
+        BinaryOp::Mul => SyntaxToken::Times,
+        BinaryOp::Div => SyntaxToken::Slash,
+        BinaryOp::Mod => SyntaxToken::Mod,
+    }
+}
+
+/// Where an expression appears inside another one.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Operand {
+    Left(BinaryOp),
+    Right(BinaryOp),
+    /// The operand of `-`, `!` or `.`.
+    Prefix,
+    /// The function being called.
+    Callee,
+}
+
+/// Whether `expr` must be parenthesized to parse back the same way as `operand`.
+/// Arguments and whole expressions never need parentheses.
+pub fn needs_parens(expr: &Expr, operand: Operand) -> bool {
+    let inner = Precedence::of(expr);
+    let (outer, associativity) = match operand {
+        Operand::Left(op) | Operand::Right(op) => match binary(op).fixity {
+            Fixity::Infix(associativity) => (binary(op).precedence, associativity),
+            fixity => unreachable!("binary operator with fixity {:?}", fixity),
+        },
+        Operand::Prefix => (Precedence::Prefix, Associativity::Right),
+        Operand::Callee => (Precedence::Call, Associativity::Left),
+    };
+    let binds_to_this_side = matches!(
+        (operand, associativity),
+        (Operand::Left(_) | Operand::Callee, Associativity::Left)
+            | (Operand::Right(_) | Operand::Prefix, Associativity::Right)
+    );
+    inner < outer || inner == outer && !binds_to_this_side
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Item, NodeId, UnaryOp};
+    use crate::lex::Lexer;
+    use crate::parser::precedence::{prefix, OPERATORS};
+    use crate::parser::Parser;
+    use crate::source::Span;
+    use proptest::prelude::*;
+
+    // Ids and spans don't matter to precedence, so every test node has the same ones.
+    fn expr(kind: ExprKind) -> Expr {
+        Expr::new(NodeId(0), Span::default(), kind)
+    }
+
+    fn var(name: &str) -> Expr {
+        expr(ExprKind::Var(name.to_string()))
+    }
+
+    fn binary_expr(op: BinaryOp) -> Expr {
+        expr(ExprKind::Binary(op, Box::new(var("a")), Box::new(var("b"))))
+    }
+
+    #[test]
+    fn covers_every_operator_token() {
+        for token in [
+            SyntaxToken::Minus,
+            SyntaxToken::Not,
+            SyntaxToken::Plus,
+            SyntaxToken::Times,
+            SyntaxToken::Slash,
+            SyntaxToken::Mod,
+            SyntaxToken::And,
+            SyntaxToken::Or,
+            SyntaxToken::Xor,
+            SyntaxToken::Eq,
+            SyntaxToken::Neq,
+            SyntaxToken::Lt,
+            SyntaxToken::Leq,
+            SyntaxToken::Gt,
+            SyntaxToken::Geq,
+            SyntaxToken::LShift,
+            SyntaxToken::RShift,
+            SyntaxToken::Dot,
+        ] {
+            assert!(
+                prefix(token).is_some() || infix(token).is_some(),
+                "{:?}",
+                token
+            );
+            if let Some(op) = binary_op(token) {
+                assert_eq!(binary(op).token, token);
+            }
+        }
+        assert!(OPERATORS
+            .windows(2)
+            .all(|w| w[0].precedence <= w[1].precedence));
+    }
+
+    #[test]
+    fn parenthesizes_only_where_needed() {
+        let sum = binary_expr(BinaryOp::Add);
+        let product = binary_expr(BinaryOp::Mul);
+        assert!(needs_parens(&sum, Operand::Left(BinaryOp::Mul)));
+        assert!(!needs_parens(&product, Operand::Right(BinaryOp::Add)));
+        assert!(!needs_parens(&sum, Operand::Left(BinaryOp::Sub)));
+        assert!(needs_parens(&sum, Operand::Right(BinaryOp::Sub)));
+        assert!(needs_parens(&sum, Operand::Prefix));
+        assert!(!needs_parens(
+            &expr(ExprKind::Deref(Box::new(var("a")))),
+            Operand::Prefix
+        ));
+        let call = expr(ExprKind::Call(Box::new(var("f")), vec![]));
+        assert!(!needs_parens(&call, Operand::Prefix));
+        assert!(!needs_parens(&call, Operand::Callee));
+        assert!(needs_parens(
+            &expr(ExprKind::Deref(Box::new(var("f")))),
+            Operand::Callee
+        ));
+        assert!(!needs_parens(&var("a"), Operand::Callee));
+    }
+
+    // Prints `expr` with only the parentheses `needs_parens` asks for.
+    fn print(expr: &Expr) -> String {
+        fn operand(expr: &Expr, position: Operand) -> String {
+            if needs_parens(expr, position) {
+                format!("({})", print(expr))
+            } else {
+                print(expr)
+            }
+        }
+        match &expr.kind {
+            ExprKind::Integer(value) => value.to_string(),
+            ExprKind::Var(name) => name.clone(),
+            ExprKind::Unary(UnaryOp::Neg, e) => format!("-{}", operand(e, Operand::Prefix)),
+            ExprKind::Unary(UnaryOp::Not, e) => format!("!{}", operand(e, Operand::Prefix)),
+            ExprKind::Deref(e) => format!(".{}", operand(e, Operand::Prefix)),
+            ExprKind::Binary(op, lhs, rhs) => format!(
+                "{} {} {}",
+                operand(lhs, Operand::Left(*op)),
+                binary_token(*op).as_str(),
+                operand(rhs, Operand::Right(*op))
+            ),
+            ExprKind::Call(callee, args) => format!(
+                "{}({})",
+                operand(callee, Operand::Callee),
+                args.iter().map(print).collect::<Vec<_>>().join(", ")
+            ),
+            kind => unreachable!("not generated: {:?}", kind),
+        }
+    }
+
+    // Drops the groups and the ids and spans, which the generated expressions lack.
+    fn normalize(e: Expr) -> Expr {
+        let strip = |e: Box<Expr>| Box::new(normalize(*e));
+        match e.kind {
+            ExprKind::Group(e) => normalize(*e),
+            ExprKind::Unary(op, e) => expr(ExprKind::Unary(op, strip(e))),
+            ExprKind::Deref(e) => expr(ExprKind::Deref(strip(e))),
+            ExprKind::Binary(op, lhs, rhs) => expr(ExprKind::Binary(op, strip(lhs), strip(rhs))),
+            ExprKind::Call(callee, args) => expr(ExprKind::Call(
+                strip(callee),
+                args.into_iter().map(normalize).collect(),
+            )),
+            kind => expr(kind),
+        }
+    }
+
+    fn parse(source: &str, lr: bool) -> Expr {
+        let tokens = Lexer::new().lex_str(source).unwrap();
+        let mut parser = Parser::new(tokens);
+        let program = if lr {
+            parser.parse_lr()
+        } else {
+            parser.parse()
+        };
+        match program.unwrap().items.remove(0) {
+            Item::Var(var) => var.value.unwrap(),
+            item => panic!("expected a variable, got {:?}", item),
+        }
+    }
+
+    fn arb_expr() -> impl Strategy<Value = Expr> {
+        let ops = OPERATORS
+            .iter()
+            .filter_map(|op| binary_op(op.token).filter(|_| op.fixity != Fixity::Prefix))
+            .collect::<Vec<_>>();
+        let leaf = prop_oneof![
+            (0u64..100).prop_map(|value| expr(ExprKind::Integer(value))),
+            "[a-c]".prop_map(|name| expr(ExprKind::Var(name))),
+        ];
+        leaf.prop_recursive(6, 48, 3, move |inner| {
+            let boxed = inner.clone().prop_map(Box::new);
+            prop_oneof![
+                (
+                    prop::sample::select(ops.clone()),
+                    boxed.clone(),
+                    boxed.clone()
+                )
+                    .prop_map(|(op, lhs, rhs)| expr(ExprKind::Binary(op, lhs, rhs))),
+                boxed
+                    .clone()
+                    .prop_map(|e| expr(ExprKind::Unary(UnaryOp::Neg, e))),
+                boxed
+                    .clone()
+                    .prop_map(|e| expr(ExprKind::Unary(UnaryOp::Not, e))),
+                boxed.clone().prop_map(|e| expr(ExprKind::Deref(e))),
+                (boxed, prop::collection::vec(inner, 0..3))
+                    .prop_map(|(callee, args)| expr(ExprKind::Call(callee, args))),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn minimal_parentheses_parse_back(e in arb_expr()) {
+            let source = format!("var x : {}", print(&e));
+            prop_assert_eq!(&normalize(parse(&source, false)), &e, "{}", source);
+            prop_assert_eq!(&normalize(parse(&source, true)), &e, "{}", source);
+        }
+    }
+}
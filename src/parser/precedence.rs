@@ -0,0 +1,112 @@
+use crate::lex::types::SyntaxToken;
+
+/// How tightly an operator binds, loosest first.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Precedence {
+    /// Below every operator, to parse a whole expression.
+    Lowest,
+    Or,
+    Xor,
+    And,
+    Equality,
+    Comparison,
+    Shift,
+    Sum,
+    Product,
+    Prefix,
+    Call,
+    /// Literals, names and parenthesized expressions, which never need parentheses.
+    Primary,
+}
+
+impl Precedence {
+    /// The next looser level, so that `pratt(p.looser())` also takes operators at `p`.
+    pub fn looser(self) -> Precedence {
+        match self {
+            Precedence::Lowest | Precedence::Or => Precedence::Lowest,
+            Precedence::Xor => Precedence::Or,
+            Precedence::And => Precedence::Xor,
+            Precedence::Equality => Precedence::And,
+            Precedence::Comparison => Precedence::Equality,
+            Precedence::Shift => Precedence::Comparison,
+            Precedence::Sum => Precedence::Shift,
+            Precedence::Product => Precedence::Sum,
+            Precedence::Prefix => Precedence::Product,
+            Precedence::Call => Precedence::Prefix,
+            Precedence::Primary => Precedence::Call,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Associativity {
+    /// `a - b - c` is `(a - b) - c`.
+    Left,
+    /// `a op b op c` would be `a op (b op c)`.
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Fixity {
+    /// Before its operand: `-x`, `!x`, `.x`.
+    Prefix,
+    Infix(Associativity),
+    /// After its operand. Only calls, whose `(` opens the argument list.
+    Postfix,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Operator {
+    pub token: SyntaxToken,
+    pub fixity: Fixity,
+    pub precedence: Precedence,
+}
+
+const fn op(token: SyntaxToken, fixity: Fixity, precedence: Precedence) -> Operator {
+    Operator {
+        token,
+        fixity,
+        precedence,
+    }
+}
+
+const LEFT: Fixity = Fixity::Infix(Associativity::Left);
+
+/// Every operator, loosest first. This drives the expression parser, and
+/// `needs_parens` reads it to print expressions with minimal parentheses.
+pub static OPERATORS: [Operator; 20] = [
+    op(SyntaxToken::Or, LEFT, Precedence::Or),
+    op(SyntaxToken::Xor, LEFT, Precedence::Xor),
+    op(SyntaxToken::And, LEFT, Precedence::And),
+    op(SyntaxToken::Eq, LEFT, Precedence::Equality),
+    op(SyntaxToken::Neq, LEFT, Precedence::Equality),
+    op(SyntaxToken::Lt, LEFT, Precedence::Comparison),
+    op(SyntaxToken::Leq, LEFT, Precedence::Comparison),
+    op(SyntaxToken::Gt, LEFT, Precedence::Comparison),
+    op(SyntaxToken::Geq, LEFT, Precedence::Comparison),
+    op(SyntaxToken::LShift, LEFT, Precedence::Shift),
+    op(SyntaxToken::RShift, LEFT, Precedence::Shift),
+    op(SyntaxToken::Plus, LEFT, Precedence::Sum),
+    op(SyntaxToken::Minus, LEFT, Precedence::Sum),
+    op(SyntaxToken::Times, LEFT, Precedence::Product),
+    op(SyntaxToken::Slash, LEFT, Precedence::Product),
+    op(SyntaxToken::Mod, LEFT, Precedence::Product),
+    op(SyntaxToken::Minus, Fixity::Prefix, Precedence::Prefix),
+    op(SyntaxToken::Not, Fixity::Prefix, Precedence::Prefix),
+    op(SyntaxToken::Dot, Fixity::Prefix, Precedence::Prefix),
+    op(SyntaxToken::LParen, Fixity::Postfix, Precedence::Call),
+];
+
+/// The operator `token` starts an operand with, if any.
+pub fn prefix(token: SyntaxToken) -> Option<&'static Operator> {
+    OPERATORS
+        .iter()
+        .find(|op| op.token == token && op.fixity == Fixity::Prefix)
+}
+
+/// The operator `token` continues an operand with, if any.
+pub fn infix(token: SyntaxToken) -> Option<&'static Operator> {
+    OPERATORS
+        .iter()
+        .find(|op| op.token == token && op.fixity != Fixity::Prefix)
+}
@@ -0,0 +1,313 @@
+use std::fmt;
+use std::ops::Range;
+
+/// A 1-based line and column. `LineIndex` counts columns in UTF-8 bytes; a
+/// `WideEncoding` says what they count in converted positions.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct LineCol {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl LineCol {
+    pub fn new(line: usize, col: usize) -> Self {
+        LineCol { line, col }
+    }
+}
+
+impl fmt::Display for LineCol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+/// Column units other than UTF-8 bytes.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum WideEncoding {
+    /// UTF-16 code units, as used by the language server protocol and most editors.
+    Utf16,
+    /// Chars, as used by the columns of tokens and diagnostics.
+    Utf32,
+}
+
+/// Converts between byte offsets and line/column positions in one file.
+///
+/// Built once per file, after which every conversion is a couple of binary
+/// searches. Only the offsets of lines and non-ASCII chars are kept, so
+/// conversions don't need the text. Columns are expected to fall on char
+/// boundaries; a wide column in the middle of a char is rounded down to it.
+///
+/// The lexer streams its input, so it builds its index a char at a time with
+/// `push` as it goes and takes token and error positions from it, using
+/// `WideEncoding::Utf32`. They always match those of the `SourceMap` index of
+/// the same text.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LineIndex {
+    len: usize,
+    line_starts: Vec<usize>,
+    // Offsets and UTF-8 lengths of the non-ASCII chars, in order.
+    wide_chars: Vec<(usize, u8)>,
+    // Entry `i` is how many more bytes the first `i` wide chars take in UTF-8
+    // than in UTF-16 and UTF-32 respectively. Has one entry more than `wide_chars`.
+    extra_utf16: Vec<usize>,
+    extra_utf32: Vec<usize>,
+}
+
+impl Default for LineIndex {
+    /// The index of an empty text.
+    fn default() -> Self {
+        LineIndex {
+            len: 0,
+            line_starts: vec![0],
+            wide_chars: Vec::new(),
+            extra_utf16: vec![0],
+            extra_utf32: vec![0],
+        }
+    }
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let mut index = LineIndex::default();
+        text.chars().for_each(|c| index.push(c));
+        index
+    }
+
+    /// Appends `c` to the end of the indexed text.
+    pub(crate) fn push(&mut self, c: char) {
+        let offset = self.len;
+        let len = c.len_utf8();
+        self.len += len;
+        if c == '\n' {
+            self.line_starts.push(self.len);
+        } else if !c.is_ascii() {
+            self.wide_chars.push((offset, len as u8));
+            self.extra_utf16
+                .push(self.extra_utf16.last().unwrap() + len - c.len_utf16());
+            self.extra_utf32
+                .push(self.extra_utf32.last().unwrap() + len - 1);
+        }
+    }
+
+    /// The length of the text in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The byte range of the given line, without its newline.
+    pub fn line_range(&self, line: usize) -> Option<Range<usize>> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self.line_starts.get(line).map_or(self.len, |next| next - 1);
+        Some(start..end)
+    }
+
+    /// The line and byte column of `offset`. Offsets past the end of the text
+    /// resolve to the end of the text.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let offset = offset.min(self.len);
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        LineCol::new(line, offset - self.line_starts[line - 1] + 1)
+    }
+
+    /// The byte offset of a line and byte column, if the line has that column.
+    /// The column just past the end of a line is included.
+    pub fn offset(&self, line_col: LineCol) -> Option<usize> {
+        let range = self.line_range(line_col.line)?;
+        let offset = range.start + line_col.col.checked_sub(1)?;
+        (offset <= range.end).then_some(offset)
+    }
+
+    // Extra bytes taken by the wide chars before wide char `i`.
+    fn extra(&self, encoding: WideEncoding, i: usize) -> usize {
+        match encoding {
+            WideEncoding::Utf16 => self.extra_utf16[i],
+            WideEncoding::Utf32 => self.extra_utf32[i],
+        }
+    }
+
+    // Number of wide chars starting before `offset`.
+    fn wide_chars_before(&self, offset: usize) -> usize {
+        self.wide_chars
+            .partition_point(|&(start, _)| start < offset)
+    }
+
+    /// Converts a byte column to a column in `encoding`.
+    pub fn to_wide(&self, encoding: WideEncoding, line_col: LineCol) -> Option<LineCol> {
+        let offset = self.offset(line_col)?;
+        let start = self.line_starts[line_col.line - 1];
+        let extra = self.extra(encoding, self.wide_chars_before(offset))
+            - self.extra(encoding, self.wide_chars_before(start));
+        Some(LineCol::new(line_col.line, line_col.col - extra))
+    }
+
+    /// Converts a column in `encoding` to a byte column.
+    pub fn to_utf8(&self, encoding: WideEncoding, line_col: LineCol) -> Option<LineCol> {
+        let range = self.line_range(line_col.line)?;
+        let col = line_col.col.checked_sub(1)?;
+        let first = self.wide_chars_before(range.start);
+        let base = self.extra(encoding, first);
+        // Binary search for the end of the line's wide chars that start before
+        // the column, by index so that `extra` needs no search of its own.
+        let (mut lo, mut hi) = (first, self.wide_chars_before(range.end));
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let start = self.wide_chars[mid].0;
+            if start - range.start - (self.extra(encoding, mid) - base) < col {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        let mut offset = range.start + col + self.extra(encoding, lo) - base;
+        if lo > first {
+            let (start, len) = self.wide_chars[lo - 1];
+            if offset < start + len as usize {
+                offset = start;
+            }
+        }
+        (offset <= range.end).then(|| LineCol::new(line_col.line, offset - range.start + 1))
+    }
+
+    /// The line and column of `offset` in `encoding`.
+    pub fn wide_line_col(&self, encoding: WideEncoding, offset: usize) -> LineCol {
+        let line_col = self.line_col(offset);
+        self.to_wide(encoding, line_col)
+            .expect("line_col returns valid positions")
+    }
+
+    /// Like `wide_line_col`, for an index of text that starts at `origin` of
+    /// a larger text rather than at 1:1, e.g. the part of a file being relexed.
+    pub(crate) fn wide_line_col_from(
+        &self,
+        origin: LineCol,
+        encoding: WideEncoding,
+        offset: usize,
+    ) -> LineCol {
+        match self.wide_line_col(encoding, offset) {
+            LineCol { line: 1, col } => LineCol::new(origin.line, origin.col + col - 1),
+            LineCol { line, col } => LineCol::new(origin.line + line - 1, col),
+        }
+    }
+
+    /// The byte offset of a line and column in `encoding`.
+    pub fn wide_offset(&self, encoding: WideEncoding, line_col: LineCol) -> Option<usize> {
+        self.offset(self.to_utf8(encoding, line_col)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::Lexer;
+    use WideEncoding::{Utf16, Utf32};
+
+    const TEXT: &str = "aé😀b\n\nx😀\ny";
+
+    #[test]
+    fn converts_between_encodings() {
+        let index = LineIndex::new(TEXT);
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.line_range(1), Some(0..8));
+        assert_eq!(index.line_range(2), Some(9..9));
+        assert_eq!(index.line_range(5), None);
+
+        // `b` is at byte 7, after a 2 byte and a 4 byte char.
+        assert_eq!(index.line_col(7), LineCol::new(1, 8));
+        assert_eq!(
+            index.to_wide(Utf16, LineCol::new(1, 8)),
+            Some(LineCol::new(1, 5))
+        );
+        assert_eq!(
+            index.to_wide(Utf32, LineCol::new(1, 8)),
+            Some(LineCol::new(1, 4))
+        );
+        assert_eq!(
+            index.to_utf8(Utf16, LineCol::new(1, 5)),
+            Some(LineCol::new(1, 8))
+        );
+        assert_eq!(
+            index.to_utf8(Utf32, LineCol::new(1, 4)),
+            Some(LineCol::new(1, 8))
+        );
+        // The middle of the surrogate pair rounds down to the start of the emoji.
+        assert_eq!(
+            index.to_utf8(Utf16, LineCol::new(1, 4)),
+            Some(LineCol::new(1, 4))
+        );
+
+        // Line 3 only counts its own wide chars.
+        assert_eq!(index.wide_line_col(Utf16, 15), LineCol::new(3, 4));
+        assert_eq!(index.wide_offset(Utf16, LineCol::new(3, 4)), Some(15));
+        assert_eq!(index.wide_offset(Utf16, LineCol::new(3, 5)), None);
+        assert_eq!(index.wide_offset(Utf32, LineCol::new(2, 1)), Some(9));
+        assert_eq!(index.line_col(100), LineCol::new(4, 2));
+    }
+
+    #[test]
+    fn round_trips_every_char_boundary() {
+        round_trip(TEXT);
+        round_trip(&format!("{}\n", "aé😀bÿ".repeat(100)).repeat(3));
+    }
+
+    fn round_trip(text: &str) {
+        let index = LineIndex::new(text);
+        for (offset, _) in text.char_indices().chain([(text.len(), ' ')]) {
+            let line_col = index.line_col(offset);
+            assert_eq!(index.offset(line_col), Some(offset));
+            for encoding in [Utf16, Utf32] {
+                let wide = index.wide_line_col(encoding, offset);
+                assert_eq!(index.wide_offset(encoding, wide), Some(offset));
+            }
+            let line_start = index.line_range(line_col.line).unwrap().start;
+            let prefix = &text[line_start..offset];
+            assert_eq!(
+                index.wide_line_col(Utf16, offset).col,
+                prefix.encode_utf16().count() + 1
+            );
+        }
+    }
+
+    #[test]
+    fn indexes_text_pushed_a_char_at_a_time() {
+        let mut index = LineIndex::default();
+        for (offset, c) in TEXT.char_indices() {
+            assert_eq!(index, LineIndex::new(&TEXT[..offset]));
+            index.push(c);
+        }
+        assert_eq!(index, LineIndex::new(TEXT));
+
+        // "x😀\ny" continues line 2 from column 3 of a larger text.
+        let rest = LineIndex::new(&TEXT[10..]);
+        let origin = LineCol::new(2, 3);
+        assert_eq!(
+            rest.wide_line_col_from(origin, Utf16, 5),
+            LineCol::new(2, 6)
+        );
+        assert_eq!(
+            rest.wide_line_col_from(origin, Utf16, 7),
+            LineCol::new(3, 2)
+        );
+    }
+
+    #[test]
+    fn matches_token_positions() {
+        let source = "var é\n  sprint(\"😀\") x\n\nÿy : 1\n";
+        let index = LineIndex::new(source);
+        for token in Lexer::new().lex_str(source).unwrap().tokens {
+            assert_eq!(
+                index.wide_line_col(Utf32, token.index()),
+                LineCol::new(token.line_no(), token.col_no()),
+                "{:?}",
+                token
+            );
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use crate::lex::{normalize, SourceReader};
+use crate::source::line_index::{LineIndex, WideEncoding};
+use crate::source::span::{FileId, Span};
+use std::borrow::Cow;
+use std::io;
+use std::path::Path;
+
+/// The text of a loaded file along with its `LineIndex`.
+#[derive(Debug)]
+pub struct SourceFile {
+    name: String,
+    source: String,
+    index: LineIndex,
+}
+
+impl SourceFile {
+    fn new(name: String, source: String) -> Self {
+        let index = LineIndex::new(&source);
+        SourceFile {
+            name,
+            source,
+            index,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn line_index(&self) -> &LineIndex {
+        &self.index
+    }
+
+    /// Resolves a byte offset to a 1-based (line, column) pair, counting columns in chars
+    /// like token positions do. Offsets past the end of the file resolve to the end of the file.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line_col = self.index.wide_line_col(WideEncoding::Utf32, offset);
+        (line_col.line, line_col.col)
+    }
+
+    /// The text of the given 1-based line, without its line ending.
+    pub fn line(&self, line_no: usize) -> Option<&str> {
+        Some(&self.source[self.index.line_range(line_no)?])
+    }
+}
+
+/// Owns every file of a compilation so that spans can be resolved back to
+/// file names, lines and columns long after lexing.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap::default()
+    }
+
+    /// Stores `source` with its byte order mark dropped and its line endings
+    /// normalised, the same text `Lexer::lex_file` and `Lexer::lex_reader` see.
+    pub fn add_file(&mut self, name: impl Into<String>, source: impl Into<String>) -> FileId {
+        let mut source = source.into();
+        if let Cow::Owned(normalized) = normalize(&source) {
+            source = normalized;
+        }
+        let id = FileId(self.files.len() as u32);
+        self.files.push(SourceFile::new(name.into(), source));
+        id
+    }
+
+    /// Reads a file through `SourceReader`, so its offsets match those of `Lexer::lex_file`.
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<FileId> {
+        let path = path.as_ref();
+        let mut reader = SourceReader::from_file(path)?;
+        let source = reader.by_ref().collect::<String>();
+        if let Some(e) = reader.take_error() {
+            return Err(e);
+        }
+        Ok(self.add_file(path.display().to_string(), source))
+    }
+
+    pub fn get(&self, file: FileId) -> Option<&SourceFile> {
+        self.files.get(file.0 as usize)
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (FileId(i as u32), f))
+    }
+
+    /// The text covered by `span`, if its file is loaded and the span is in bounds.
+    pub fn snippet(&self, span: Span) -> Option<&str> {
+        self.get(span.file)?.source.get(span.range())
+    }
+
+    /// Resolves the start of `span` to its file name, line and column.
+    pub fn lookup(&self, span: Span) -> Option<(&str, usize, usize)> {
+        let file = self.get(span.file)?;
+        let (line, col) = file.line_col(span.start);
+        Some((file.name(), line, col))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_offsets_across_files() {
+        let mut map = SourceMap::new();
+        let a = map.add_file("a.t", "var x\nfun f() {\n}\n");
+        let b = map.add_file("b.t", "é\nÿx");
+        assert_eq!(map.lookup(Span::new(a, 0, 3)), Some(("a.t", 1, 1)));
+        assert_eq!(map.lookup(Span::new(a, 10, 11)), Some(("a.t", 2, 5)));
+        assert_eq!(map.lookup(Span::new(a, 6, 6)), Some(("a.t", 2, 1)));
+        assert_eq!(map.lookup(Span::new(b, 5, 6)), Some(("b.t", 2, 2)));
+        assert_eq!(map.snippet(Span::new(b, 3, 6)), Some("ÿx"));
+        assert_eq!(map.get(a).unwrap().line(2), Some("fun f() {"));
+        assert_eq!(map.get(b).unwrap().line(3), None);
+    }
+
+    #[test]
+    fn stores_normalised_text() {
+        let mut map = SourceMap::new();
+        let file = map.add_file("crlf.t", "\u{FEFF}var x\r\ny\rz\r\n");
+        assert_eq!(map.get(file).unwrap().source(), "var x\ny\nz\n");
+        assert_eq!(map.lookup(Span::new(file, 8, 9)), Some(("crlf.t", 3, 1)));
+    }
+}
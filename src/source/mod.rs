@@ -0,0 +1,7 @@
+pub(crate) mod line_index;
+pub(crate) mod map;
+pub(crate) mod span;
+
+pub use line_index::{LineCol, LineIndex, WideEncoding};
+pub use map::{SourceFile, SourceMap};
+pub use span::{FileId, Span};
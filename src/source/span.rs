@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Identifies a file loaded into a `SourceMap`.
+#[derive(
+    Debug, Default, Eq, PartialEq, Hash, Clone, Copy, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct FileId(pub(crate) u32);
+
+/// A byte range `start..end` in one file.
+#[derive(Debug, Default, Eq, PartialEq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct Span {
+    pub file: FileId,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(file: FileId, start: usize, end: usize) -> Self {
+        debug_assert!(start <= end, "span start {} is past its end {}", start, end);
+        Span { file, start, end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The smallest span covering both `self` and `other`, which must be in the same file.
+    pub fn to(self, other: Span) -> Span {
+        debug_assert_eq!(self.file, other.file);
+        Span::new(
+            self.file,
+            self.start.min(other.start),
+            self.end.max(other.end),
+        )
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
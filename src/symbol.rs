@@ -0,0 +1,98 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+/// An interned string. Symbols are cheap to copy, compare and hash, and are
+/// shared by every stage of the compiler through one global interner.
+#[derive(Eq, PartialEq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+// Interned strings are leaked so that `Symbol::as_str` can hand out `&'static str`.
+// The set of names in a program is small, and it lives as long as the compiler does.
+#[derive(Default)]
+struct Interner {
+    symbols: HashMap<&'static str, Symbol>,
+    strings: Vec<&'static str>,
+}
+
+impl Interner {
+    fn intern(&mut self, s: &str) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(s) {
+            return symbol;
+        }
+        let symbol = Symbol(self.strings.len() as u32);
+        let s: &'static str = Box::leak(s.to_owned().into_boxed_str());
+        self.strings.push(s);
+        self.symbols.insert(s, symbol);
+        symbol
+    }
+}
+
+lazy_static! {
+    static ref INTERNER: Mutex<Interner> = Mutex::new(Interner::default());
+}
+
+impl Symbol {
+    /// Returns the symbol for `s`, only allocating the first time `s` is seen.
+    pub fn intern(s: &str) -> Symbol {
+        INTERNER.lock().unwrap().intern(s)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        INTERNER.lock().unwrap().strings[self.0 as usize]
+    }
+
+    pub fn as_u32(&self) -> u32 {
+        self.0
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(s: &str) -> Self {
+        Symbol::intern(s)
+    }
+}
+
+// Symbol indices are only meaningful within one process, so symbols travel as
+// their strings.
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        Ok(Symbol::intern(&s))
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interning_is_idempotent() {
+        let a = Symbol::intern("interning_is_idempotent");
+        let b = Symbol::intern(&String::from("interning_is_idempotent"));
+        assert_eq!(a, b);
+        assert_ne!(a, Symbol::intern("something_else"));
+        assert_eq!(a.as_str(), "interning_is_idempotent");
+        assert_eq!(a.to_string(), "interning_is_idempotent");
+    }
+}